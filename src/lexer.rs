@@ -0,0 +1,1501 @@
+//! Splits source text into tokens.
+//!
+//! The lexer is deliberately low level: a [`Token`] only records its kind and
+//! where it is in the input, and lexing never fails. Malformed input is
+//! reported through the token kinds themselves (e.g. an unterminated string
+//! literal), leaving diagnostics to later stages.
+
+mod cursor;
+mod source_map;
+#[cfg(test)]
+mod tests;
+
+pub use cursor::{Cursor, EOF_CHAR};
+pub use source_map::{FileId, SourceMap};
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::ops::Range;
+
+use LiteralKind::*;
+use TokenKind::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Length of the token in bytes.
+    pub len: usize,
+    pub span: Span,
+    location: Location,
+}
+
+impl Token {
+    fn new(kind: TokenKind, span: Span, location: Location) -> Token {
+        Token {
+            kind,
+            len: span.hi - span.lo,
+            span,
+            location,
+        }
+    }
+
+    /// Where the token starts.
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    /// The text of the token, given the `src` it was lexed from.
+    pub fn text<'a>(&self, src: &'a str) -> &'a str {
+        debug_assert!(
+            self.span.hi <= src.len(),
+            "span {:?} is out of bounds for input of length {}",
+            self.span,
+            src.len()
+        );
+        &src[self.span.lo..self.span.hi]
+    }
+}
+
+/// A range of byte offsets into the input, `lo` inclusive and `hi` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub lo: usize,
+    pub hi: usize,
+}
+
+impl Span {
+    /// An empty span for things with no place in the input, such as nodes
+    /// made up by the compiler.
+    pub fn dummy() -> Span {
+        Span::default()
+    }
+
+    /// The span from the start of `self` to the end of `other`, including
+    /// anything in between.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            lo: self.lo,
+            hi: other.hi,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenKind {
+    /// `// comment` or `/// doc`, not including the trailing newline.
+    LineComment { kind: CommentKind },
+    /// `/* comment */` or `/** doc */`. Block comments nest, so `/* /* */` is
+    /// unterminated.
+    BlockComment { kind: CommentKind, terminated: bool },
+    /// A run of whitespace characters, see [`is_whitespace`].
+    Whitespace,
+    /// An identifier such as `main` or `_x1`.
+    Identifier,
+    /// A reserved word such as `fn`.
+    Keyword(Keyword),
+    /// A lifetime such as `'a`.
+    Lifetime,
+    /// A literal, e.g. `"hello"` or `42`.
+    Literal { kind: LiteralKind },
+    /// `;`
+    Semi,
+    /// `(`
+    OpenParen,
+    /// `)`
+    CloseParen,
+    /// `{`
+    OpenBrace,
+    /// `}`
+    CloseBrace,
+    /// `[`
+    OpenBracket,
+    /// `]`
+    CloseBracket,
+    /// `,`
+    Comma,
+    /// `.`
+    Dot,
+    /// `..`
+    DotDot,
+    /// `..=`
+    DotDotEq,
+    /// `!`
+    Exclam,
+    /// `+`
+    Plus,
+    /// `-`
+    Minus,
+    /// `*`
+    Star,
+    /// `/`
+    Slash,
+    /// `%`
+    Percent,
+    /// `+=`
+    PlusEq,
+    /// `-=`
+    MinusEq,
+    /// `*=`
+    StarEq,
+    /// `/=`
+    SlashEq,
+    /// `%=`
+    PercentEq,
+    /// `=`
+    Eq,
+    /// `:`
+    Colon,
+    /// `::`
+    PathSep,
+    /// `->`
+    Arrow,
+    /// `=>`
+    FatArrow,
+    /// `<`
+    Lt,
+    /// `>`
+    Gt,
+    /// `<=`
+    Le,
+    /// `>=`
+    Ge,
+    /// `==`
+    EqEq,
+    /// `!=`
+    Ne,
+    /// `<<`
+    Shl,
+    /// `>>`
+    Shr,
+    /// `&`
+    And,
+    /// `&&`
+    AndAnd,
+    /// `|`
+    Or,
+    /// `||`
+    OrOr,
+    /// `^`
+    Caret,
+    /// `?`
+    Question,
+    /// `#`, as at the start of an attribute like `#[test]` or `#![allow]`.
+    Pound,
+    /// `@`
+    At,
+    /// `$`, as in the macro metavariable `$x`.
+    Dollar,
+    /// A character the lexer does not recognize.
+    Unknown(char),
+    /// `#!/usr/bin/env rc`, only recognized as the very first line of the
+    /// input.
+    Shebang,
+    /// End of input, only produced by [`tokenize_with_eof`].
+    Eof,
+}
+
+/// Whether a comment is documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommentKind {
+    /// `None` for an ordinary comment.
+    pub doc: Option<DocStyle>,
+}
+
+/// Which item a doc comment documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DocStyle {
+    /// `/// doc` or `/** doc */`, documenting the item that follows.
+    Outer,
+    /// `//! doc` or `/*! doc */`, documenting the enclosing item.
+    Inner,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LiteralKind {
+    /// `42`, `0xFF`, `7u8`. `empty_digits` is set for a prefix with no
+    /// digits after it, like `0x`. `suffix_start` is the offset of the type
+    /// suffix within the token, or the token's length if it has none.
+    Int {
+        base: Base,
+        empty_digits: bool,
+        suffix_start: usize,
+    },
+    /// `1.0`, `1.`, `2.5e10f32`. `empty_exponent` is set for an exponent with
+    /// no digits after it, like `1e`. `suffix_start` is as for `Int`.
+    Float {
+        base: Base,
+        empty_exponent: bool,
+        suffix_start: usize,
+    },
+    /// `'a'`, `'a`
+    Char { terminated: bool },
+    /// `b'a'`, `b'a`
+    Byte { terminated: bool },
+    /// `b"abc"`, `b"abc`
+    ByteStr { terminated: bool },
+    /// `r"abc"`, `r#"a"b"#`. `n_hashes` saturates at 255.
+    RawStr { n_hashes: u8, terminated: bool },
+    /// `"abc"`, `"abc`. `has_invalid_escape` is set if the string contains
+    /// an unknown escape such as `\q`.
+    Str {
+        terminated: bool,
+        has_invalid_escape: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Keyword {
+    Fn,
+    Let,
+    If,
+    Else,
+    Return,
+    While,
+    True,
+    False,
+}
+
+impl Keyword {
+    const ALL: [Keyword; 8] = [
+        Keyword::Fn,
+        Keyword::Let,
+        Keyword::If,
+        Keyword::Else,
+        Keyword::Return,
+        Keyword::While,
+        Keyword::True,
+        Keyword::False,
+    ];
+
+    /// Returns the keyword spelled `text`, if any.
+    pub fn lookup(text: &str) -> Option<Keyword> {
+        Keyword::ALL.into_iter().find(|kw| kw.as_str() == text)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Keyword::Fn => "fn",
+            Keyword::Let => "let",
+            Keyword::If => "if",
+            Keyword::Else => "else",
+            Keyword::Return => "return",
+            Keyword::While => "while",
+            Keyword::True => "true",
+            Keyword::False => "false",
+        }
+    }
+}
+
+/// The base of an integer literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Base {
+    /// `0b`
+    Binary,
+    /// `0o`
+    Octal,
+    Decimal,
+    /// `0x`
+    Hexadecimal,
+}
+
+impl Base {
+    pub fn radix(self) -> u32 {
+        match self {
+            Base::Binary => 2,
+            Base::Octal => 8,
+            Base::Decimal => 10,
+            Base::Hexadecimal => 16,
+        }
+    }
+}
+
+impl TokenKind {
+    /// Whether the token is whitespace or a comment, which carry no meaning
+    /// for the parser.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, Whitespace | LineComment { .. } | BlockComment { .. })
+    }
+
+    /// Whether the token is `(`, `{` or `[`.
+    pub fn is_open_delim(&self) -> bool {
+        self.matching_close().is_some()
+    }
+
+    /// Whether the token is `)`, `}` or `]`.
+    pub fn is_close_delim(&self) -> bool {
+        matches!(self, CloseParen | CloseBrace | CloseBracket)
+    }
+
+    /// The delimiter that closes this one, if it is an opening delimiter.
+    pub fn matching_close(&self) -> Option<TokenKind> {
+        match self {
+            OpenParen => Some(CloseParen),
+            OpenBrace => Some(CloseBrace),
+            OpenBracket => Some(CloseBracket),
+            _ => None,
+        }
+    }
+
+    /// A fixed name for the kind of token, ignoring any data it carries,
+    /// such as "identifier" or "(".
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineComment { .. } => "line comment",
+            BlockComment { .. } => "block comment",
+            Keyword(_) => "keyword",
+            Literal { .. } => "literal",
+            Unknown(_) => "unknown character",
+            Whitespace => "whitespace",
+            Identifier => "identifier",
+            Lifetime => "lifetime",
+            Semi => ";",
+            OpenParen => "(",
+            CloseParen => ")",
+            OpenBrace => "{",
+            CloseBrace => "}",
+            OpenBracket => "[",
+            CloseBracket => "]",
+            Comma => ",",
+            Dot => ".",
+            DotDot => "..",
+            DotDotEq => "..=",
+            Exclam => "!",
+            Plus => "+",
+            Minus => "-",
+            Star => "*",
+            Slash => "/",
+            Percent => "%",
+            PlusEq => "+=",
+            MinusEq => "-=",
+            StarEq => "*=",
+            SlashEq => "/=",
+            PercentEq => "%=",
+            Eq => "=",
+            Colon => ":",
+            PathSep => "::",
+            Arrow => "->",
+            FatArrow => "=>",
+            Lt => "<",
+            Gt => ">",
+            Le => "<=",
+            Ge => ">=",
+            EqEq => "==",
+            Ne => "!=",
+            Shl => "<<",
+            Shr => ">>",
+            And => "&",
+            AndAnd => "&&",
+            Or => "|",
+            OrOr => "||",
+            Caret => "^",
+            Question => "?",
+            Pound => "#",
+            At => "@",
+            Dollar => "$",
+            Shebang => "shebang",
+            Eof => "end of input",
+        }
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineComment { kind } => write!(f, "{kind}{}", self.name()),
+            BlockComment { kind, terminated } => {
+                write!(f, "{kind}{} ({})", self.name(), terminated_str(*terminated))
+            }
+            Keyword(kw) => write!(f, "{} `{}`", self.name(), kw.as_str()),
+            Literal { kind } => write!(f, "{kind}"),
+            Unknown(c) => write!(f, "{} `{c}`", self.name()),
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+/// Prefixes the comment's kind of token, e.g. "outer doc " for `///`.
+impl fmt::Display for CommentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.doc {
+            None => Ok(()),
+            Some(DocStyle::Outer) => f.write_str("outer doc "),
+            Some(DocStyle::Inner) => f.write_str("inner doc "),
+        }
+    }
+}
+
+impl fmt::Display for LiteralKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Int { base, .. } => write!(f, "{base} integer literal"),
+            Float { .. } => f.write_str("float literal"),
+            Char { terminated } => {
+                write!(f, "char literal ({})", terminated_str(*terminated))
+            }
+            Byte { terminated } => {
+                write!(f, "byte literal ({})", terminated_str(*terminated))
+            }
+            ByteStr { terminated } => {
+                write!(f, "byte string literal ({})", terminated_str(*terminated))
+            }
+            RawStr { terminated, .. } => {
+                write!(f, "raw string literal ({})", terminated_str(*terminated))
+            }
+            Str { terminated, .. } => {
+                write!(f, "string literal ({})", terminated_str(*terminated))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Base {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Base::Binary => "binary",
+            Base::Octal => "octal",
+            Base::Decimal => "decimal",
+            Base::Hexadecimal => "hexadecimal",
+        })
+    }
+}
+
+fn terminated_str(terminated: bool) -> &'static str {
+    if terminated {
+        "terminated"
+    } else {
+        "unterminated"
+    }
+}
+
+/// A line and column in the input, both starting at 1.
+///
+/// Columns count characters, so by default a tab advances the column by one.
+/// [`TokenizeOptions::tab_width`] changes that.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Location {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Default for Location {
+    fn default() -> Location {
+        Location { line: 1, col: 1 }
+    }
+}
+
+impl Location {
+    /// Moves the location past `text`, with tab stops every `tab_width`
+    /// columns.
+    fn advance(&mut self, text: &str, tab_width: usize) {
+        let tab_width = tab_width.max(1);
+        for c in text.chars() {
+            match c {
+                '\n' => {
+                    self.line += 1;
+                    self.col = 1;
+                }
+                '\t' => self.col = (self.col - 1) / tab_width * tab_width + tab_width + 1,
+                _ => self.col += 1,
+            }
+        }
+    }
+}
+
+/// Settings for [`tokenize_with_options`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenizeOptions {
+    /// Columns between tab stops when computing [`Location`]s. A width of 0
+    /// is treated as 1.
+    pub tab_width: usize,
+    /// Merge each run of unrecognized characters into one [`Unknown`] token,
+    /// which carries the first of them.
+    pub coalesce_unknown: bool,
+    /// The words lexed as [`Keyword`]s rather than identifiers.
+    pub keywords: Keywords,
+}
+
+impl Default for TokenizeOptions {
+    fn default() -> TokenizeOptions {
+        TokenizeOptions {
+            tab_width: 1,
+            coalesce_unknown: false,
+            keywords: Keywords::default(),
+        }
+    }
+}
+
+/// A set of keyword spellings, for dialects that spell keywords differently,
+/// e.g. `func` for [`Keyword::Fn`].
+///
+/// The default is the built-in set, as spelled by [`Keyword::as_str`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Keywords {
+    /// `None` for the built-in set.
+    custom: Option<&'static [(&'static str, Keyword)]>,
+}
+
+impl Keywords {
+    /// Recognizes exactly the spellings in `table`. Words not in it,
+    /// including built-in keywords, lex as identifiers.
+    pub const fn new(table: &'static [(&'static str, Keyword)]) -> Keywords {
+        Keywords {
+            custom: Some(table),
+        }
+    }
+
+    /// Returns the keyword spelled `text`, if any.
+    pub fn lookup(&self, text: &str) -> Option<Keyword> {
+        match self.custom {
+            None => Keyword::lookup(text),
+            Some(table) => table
+                .iter()
+                .find(|(spelling, _)| *spelling == text)
+                .map(|&(_, kw)| kw),
+        }
+    }
+}
+
+/// Lazily splits `input` into tokens.
+pub fn tokenize(input: &str) -> Tokenizer<'_> {
+    Tokenizer::new(input)
+}
+
+/// Like [`tokenize`], but with non-default [`TokenizeOptions`].
+pub fn tokenize_with_options(input: &str, options: TokenizeOptions) -> Tokenizer<'_> {
+    Tokenizer::with_options(input, options)
+}
+
+/// Iterator over the tokens of some input, returned by [`tokenize`].
+pub struct Tokenizer<'a> {
+    input: &'a str,
+    cursor: Cursor<'a>,
+    /// Byte offset of the next token.
+    offset: usize,
+    /// Location of the next token.
+    location: Location,
+    options: TokenizeOptions,
+    /// Whether `input` is the start of the source, where a shebang may be.
+    at_start: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(input: &'a str) -> Tokenizer<'a> {
+        Tokenizer::with_options(input, TokenizeOptions::default())
+    }
+
+    pub fn with_options(input: &'a str, options: TokenizeOptions) -> Tokenizer<'a> {
+        Tokenizer {
+            input,
+            cursor: Cursor::new(input),
+            offset: 0,
+            location: Location::default(),
+            options,
+            at_start: true,
+        }
+    }
+
+    /// Tokenizes `input` as the continuation of some earlier source, with
+    /// its first token at `location`.
+    fn resume(input: &'a str, location: Location) -> Tokenizer<'a> {
+        Tokenizer {
+            location,
+            at_start: false,
+            ..Tokenizer::new(input)
+        }
+    }
+}
+
+impl Iterator for Tokenizer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if self.cursor.is_empty() {
+            return None;
+        }
+        self.cursor.reset_len_consumed();
+        let kind = if self.at_start && self.offset == 0 && starts_with_shebang(self.input) {
+            self.cursor.adv_until(|c| c != '\n');
+            Shebang
+        } else {
+            self.cursor.eat_token(&self.options.keywords)
+        };
+        if matches!(kind, Unknown(_)) && self.options.coalesce_unknown {
+            self.eat_unknown_run();
+        }
+        let span = Span {
+            lo: self.offset,
+            hi: self.offset + self.cursor.len_consumed(),
+        };
+        let token = Token::new(kind, span, self.location);
+        self.offset = span.hi;
+        self.location
+            .advance(&self.input[span.lo..span.hi], self.options.tab_width);
+        Some(token)
+    }
+
+    /// Every token is at least one byte long, and there is at least one
+    /// while any input is left.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.cursor.remaining().len();
+        (remaining.min(1), Some(remaining))
+    }
+}
+
+impl std::iter::FusedIterator for Tokenizer<'_> {}
+
+impl Tokenizer<'_> {
+    /// Eats characters for as long as each would lex as [`Unknown`] on its
+    /// own.
+    fn eat_unknown_run(&mut self) {
+        loop {
+            let checkpoint = self.cursor.checkpoint();
+            if !matches!(self.cursor.eat_token(&self.options.keywords), Unknown(_)) {
+                self.cursor.restore(checkpoint);
+                break;
+            }
+        }
+    }
+}
+
+/// Whether `input` starts with a `#!` line. `#![` starts an inner attribute
+/// instead.
+fn starts_with_shebang(input: &str) -> bool {
+    input.starts_with("#!") && !input.starts_with("#![")
+}
+
+/// Like [`tokenize`], but ends with a single empty [`Eof`] token.
+pub fn tokenize_with_eof(input: &str) -> impl Iterator<Item = Token> + '_ {
+    let mut tokens = tokenize(input);
+    let mut finished = false;
+    std::iter::from_fn(move || {
+        if finished {
+            return None;
+        }
+        if let Some(token) = tokens.next() {
+            return Some(token);
+        }
+        finished = true;
+        let end = Span {
+            lo: tokens.offset,
+            hi: tokens.offset,
+        };
+        Some(Token::new(Eof, end, tokens.location))
+    })
+}
+
+/// Lazily tokenizes everything `reader` produces, without reading it all into
+/// memory first.
+///
+/// Tokens are the same as [`tokenize`] would give for the whole input. Input
+/// that is not UTF-8 fails with [`io::ErrorKind::InvalidData`], and the
+/// iterator ends after the first error.
+pub fn tokenize_reader<R: io::Read>(reader: R) -> impl Iterator<Item = io::Result<Token>> {
+    ReaderTokenizer {
+        reader,
+        buf: String::new(),
+        partial_char: Vec::new(),
+        base: 0,
+        location: Location::default(),
+        ready: VecDeque::new(),
+        lex_at: 0,
+        eof: false,
+        failed: false,
+    }
+}
+
+/// Iterator returned by [`tokenize_reader`].
+///
+/// Input is read in chunks into `buf`. A token is only handed out once the
+/// buffer extends past it, since more input could still make it longer. The
+/// unfinished tail of the buffer is lexed again once the buffer has doubled,
+/// so a token spanning many chunks is lexed a linear amount overall.
+struct ReaderTokenizer<R> {
+    reader: R,
+    /// Input that has been read but not yet handed out as tokens.
+    buf: String,
+    /// The start of a UTF-8 sequence split across reads.
+    partial_char: Vec<u8>,
+    /// Byte offset of `buf` in the whole input.
+    base: usize,
+    /// Location of the start of `buf`.
+    location: Location,
+    ready: VecDeque<Token>,
+    /// How long `buf` has to grow before it is worth lexing again.
+    lex_at: usize,
+    eof: bool,
+    failed: bool,
+}
+
+/// How many bytes [`ReaderTokenizer`] asks its reader for at a time.
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+impl<R: io::Read> ReaderTokenizer<R> {
+    /// Reads the next chunk onto the end of `buf`, setting `eof` once the
+    /// reader is exhausted.
+    fn fill_buf(&mut self) -> io::Result<()> {
+        let mut chunk = [0; READ_CHUNK_SIZE];
+        let n = loop {
+            match self.reader.read(&mut chunk) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        };
+        if n == 0 {
+            self.eof = true;
+            if !self.partial_char.is_empty() {
+                return Err(invalid_utf8());
+            }
+            return Ok(());
+        }
+        self.partial_char.extend_from_slice(&chunk[..n]);
+        let valid_up_to = match std::str::from_utf8(&self.partial_char) {
+            Ok(text) => text.len(),
+            // `error_len` is `None` when the bytes are only cut short.
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => return Err(invalid_utf8()),
+        };
+        let text = std::str::from_utf8(&self.partial_char[..valid_up_to]).unwrap();
+        self.buf.push_str(text);
+        self.partial_char.drain(..valid_up_to);
+        Ok(())
+    }
+
+    /// Moves the tokens of `buf` that no more input could change into
+    /// `ready`.
+    fn lex_buf(&mut self) {
+        let mut tokens = if self.base == 0 {
+            Tokenizer::new(&self.buf)
+        } else {
+            Tokenizer::resume(&self.buf, self.location)
+        };
+        let mut consumed = 0;
+        while let Some(token) = tokens.next() {
+            if token.span.hi == self.buf.len() && !self.eof {
+                self.location = token.location;
+                break;
+            }
+            let span = Span {
+                lo: self.base + token.span.lo,
+                hi: self.base + token.span.hi,
+            };
+            self.ready
+                .push_back(Token::new(token.kind, span, token.location));
+            consumed = token.span.hi;
+            self.location = tokens.location;
+        }
+        self.buf.drain(..consumed);
+        self.base += consumed;
+        self.lex_at = 2 * self.buf.len();
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "input is not valid UTF-8")
+}
+
+impl<R: io::Read> Iterator for ReaderTokenizer<R> {
+    type Item = io::Result<Token>;
+
+    fn next(&mut self) -> Option<io::Result<Token>> {
+        loop {
+            if let Some(token) = self.ready.pop_front() {
+                return Some(Ok(token));
+            }
+            if self.failed || (self.eof && self.buf.is_empty()) {
+                return None;
+            }
+            if let Err(e) = self.fill_buf() {
+                self.failed = true;
+                return Some(Err(e));
+            }
+            if self.eof || self.buf.len() >= self.lex_at {
+                self.lex_buf();
+            }
+        }
+    }
+}
+
+/// Eagerly tokenizes `input`.
+pub fn tokenize_to_vec(input: &str) -> Vec<Token> {
+    tokenize(input).collect()
+}
+
+/// Like [`tokenize_to_vec`], but clears and fills `out`, reusing its
+/// allocation.
+pub fn tokenize_into(input: &str, out: &mut Vec<Token>) {
+    out.clear();
+    out.extend(tokenize(input));
+}
+
+/// Counts the tokens of `input` of each kind, keyed by
+/// [`TokenKind::name`].
+pub fn token_histogram(input: &str) -> HashMap<&'static str, usize> {
+    let mut counts = HashMap::new();
+    for token in tokenize(input) {
+        *counts.entry(token.kind.name()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// How many bytes past the end of a token the lexer may look before ending
+/// it: two characters of up to four bytes each.
+const MAX_LOOKAHEAD: usize = 8;
+
+/// Re-tokenizes `src` after an edit, reusing what it can of `old_tokens`.
+///
+/// `old_tokens` are all the tokens of the source before the edit, and `edit`
+/// is the byte range of that source which was replaced to give `src`. Lexing
+/// restarts at the last token the edit cannot have changed and stops once a
+/// new token starts where an old one did after the edit, so the result is
+/// the same as [`tokenize_to_vec`] on `src`.
+pub fn relex(old_tokens: &[Token], src: &str, edit: Range<usize>) -> Vec<Token> {
+    let old_len = old_tokens.last().map_or(0, |token| token.span.hi);
+    // Where the text after the edit starts in `src`.
+    let new_edit_end = edit.end + src.len() - old_len;
+    let restart = old_tokens
+        .iter()
+        .position(|token| token.span.hi + MAX_LOOKAHEAD > edit.start)
+        .unwrap_or(old_tokens.len());
+    let mut tokens = old_tokens[..restart].to_vec();
+    let (base, relexed) = match old_tokens.get(restart) {
+        Some(token) if restart > 0 => (
+            token.span.lo,
+            Tokenizer::resume(&src[token.span.lo..], token.location),
+        ),
+        _ => (0, Tokenizer::new(src)),
+    };
+    for token in relexed {
+        let span = Span {
+            lo: base + token.span.lo,
+            hi: base + token.span.hi,
+        };
+        if span.lo >= new_edit_end {
+            let old_lo = span.lo - new_edit_end + edit.end;
+            let resync = old_tokens
+                .binary_search_by_key(&old_lo, |t| t.span.lo)
+                // A shebang depends on where it is, not just its text.
+                .ok()
+                .filter(|&i| old_tokens[i].kind != Shebang);
+            if let Some(i) = resync {
+                let (old_anchor, new_anchor) = (old_tokens[i].location, token.location);
+                tokens.extend(old_tokens[i..].iter().map(|old| {
+                    let mut location = old.location;
+                    if location.line == old_anchor.line {
+                        location.col = location.col - old_anchor.col + new_anchor.col;
+                    }
+                    location.line = location.line - old_anchor.line + new_anchor.line;
+                    let span = Span {
+                        lo: old.span.lo - old_lo + span.lo,
+                        hi: old.span.hi - old_lo + span.lo,
+                    };
+                    Token::new(old.kind, span, location)
+                }));
+                return tokens;
+            }
+        }
+        tokens.push(Token::new(token.kind, span, token.location));
+    }
+    tokens
+}
+
+/// Like [`tokenize_to_vec`], but fails with every parenthesis or brace that
+/// has no partner, in input order.
+///
+/// A closing delimiter matches the innermost open delimiter of its kind, and
+/// any delimiters opened after that one are reported, so in `{(}` only the
+/// `(` is unmatched.
+pub fn check_delims(input: &str) -> Result<Vec<Token>, Vec<Token>> {
+    let tokens = tokenize_to_vec(input);
+    let mut open = Vec::new();
+    let mut unmatched = Vec::new();
+    for token in &tokens {
+        let expected_open = match token.kind {
+            OpenParen | OpenBrace => {
+                open.push(*token);
+                continue;
+            }
+            CloseParen => OpenParen,
+            CloseBrace => OpenBrace,
+            _ => continue,
+        };
+        match open.iter().rposition(|t| t.kind == expected_open) {
+            Some(i) => {
+                unmatched.extend(open.drain(i + 1..));
+                open.pop();
+            }
+            None => unmatched.push(*token),
+        }
+    }
+    if open.is_empty() && unmatched.is_empty() {
+        return Ok(tokens);
+    }
+    unmatched.extend(open);
+    unmatched.sort_by_key(|token| token.span.lo);
+    Err(unmatched)
+}
+
+/// Counts the lines covered by `tokens`, which were lexed from `src`.
+///
+/// Newlines inside any token count, so a string literal spanning two lines
+/// adds a line too. A last line without a trailing newline still counts.
+pub fn count_lines(tokens: &[Token], src: &str) -> usize {
+    let newlines: usize = tokens
+        .iter()
+        .map(|token| token.text(src).matches('\n').count())
+        .sum();
+    let unterminated_last_line = tokens
+        .last()
+        .is_some_and(|token| !token.text(src).ends_with('\n'));
+    newlines + usize::from(unterminated_last_line)
+}
+
+/// Summary counts over the tokens of some input, see [`lex_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LexStats {
+    pub n_tokens: usize,
+    /// Lines covered, counted as by [`count_lines`].
+    pub n_lines: usize,
+    /// String, raw string and byte string literals missing their closing
+    /// quote.
+    pub n_unterminated_strings: usize,
+    pub n_unknown: usize,
+}
+
+/// Computes [`LexStats`] for `input` in one pass, without collecting the
+/// tokens.
+pub fn lex_stats(input: &str) -> LexStats {
+    let mut ends_with_newline = true;
+    let mut stats = tokenize(input).fold(LexStats::default(), |mut stats, token| {
+        let text = token.text(input);
+        stats.n_tokens += 1;
+        stats.n_lines += text.matches('\n').count();
+        ends_with_newline = text.ends_with('\n');
+        match token.kind {
+            Literal {
+                kind:
+                    Str {
+                        terminated: false, ..
+                    }
+                    | RawStr {
+                        terminated: false, ..
+                    }
+                    | ByteStr { terminated: false },
+            } => stats.n_unterminated_strings += 1,
+            Unknown(_) => stats.n_unknown += 1,
+            _ => {}
+        }
+        stats
+    });
+    stats.n_lines += usize::from(!ends_with_newline);
+    stats
+}
+
+/// Drops whitespace and comments, leaving the tokens the parser cares about.
+pub fn strip_trivia(tokens: impl Iterator<Item = Token>) -> impl Iterator<Item = Token> {
+    tokens.filter(|token| !token.kind.is_trivia())
+}
+
+/// The first problem found by [`try_tokenize`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    /// Byte offset of the start of the offending token.
+    pub offset: usize,
+    pub kind: LexErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexErrorKind {
+    UnknownChar(char),
+    UnterminatedString,
+    /// A block comment that is never closed. `open` is the span of its
+    /// outermost `/*`, which nested comments leave open too.
+    UnterminatedBlockComment {
+        open: Span,
+    },
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            LexErrorKind::UnknownChar(c) => {
+                write!(f, "unknown character {c:?} at offset {}", self.offset)
+            }
+            LexErrorKind::UnterminatedString => {
+                write!(f, "unterminated string starting at offset {}", self.offset)
+            }
+            LexErrorKind::UnterminatedBlockComment { open } => {
+                write!(
+                    f,
+                    "unterminated block comment started at offset {}",
+                    open.lo
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// Eagerly tokenizes `input`, stopping at the first unknown character,
+/// unterminated string or unterminated block comment.
+pub fn try_tokenize(input: &str) -> Result<Vec<Token>, LexError> {
+    tokenize(input)
+        .map(|token| {
+            let kind = match token.kind {
+                Unknown(c) => LexErrorKind::UnknownChar(c),
+                Literal {
+                    kind:
+                        Str {
+                            terminated: false, ..
+                        }
+                        | RawStr {
+                            terminated: false, ..
+                        }
+                        | ByteStr { terminated: false },
+                } => LexErrorKind::UnterminatedString,
+                BlockComment {
+                    terminated: false, ..
+                } => LexErrorKind::UnterminatedBlockComment {
+                    open: Span {
+                        lo: token.span.lo,
+                        hi: token.span.lo + "/*".len(),
+                    },
+                },
+                _ => return Ok(token),
+            };
+            Err(LexError {
+                offset: token.span.lo,
+                kind,
+            })
+        })
+        .collect()
+}
+
+/// Renders a rustc-style snippet of the line of `input` containing the start
+/// of `span`, with `^`s under the part of `span` on that line.
+///
+/// The message is taken from `token`'s kind, e.g. "string literal
+/// (unterminated)", so `span` is usually `token.span` or a part of it.
+pub fn render_lex_diagnostic(input: &str, token: &Token, span: Span) -> String {
+    let line_start = input[..span.lo].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = input[span.lo..]
+        .find('\n')
+        .map_or(input.len(), |i| span.lo + i);
+    let line = &input[line_start..line_end];
+    let line_number = input[..line_start].matches('\n').count() + 1;
+    let column = input[line_start..span.lo].chars().count() + 1;
+
+    // Keep tabs in the padding so the carets line up however tabs render.
+    let padding: String = input[line_start..span.lo]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let underlined = input[span.lo..span.hi.clamp(span.lo, line_end)]
+        .chars()
+        .count()
+        .max(1);
+
+    let gutter = " ".repeat(line_number.to_string().len());
+    format!(
+        "error: {}\n{gutter}--> {line_number}:{column}\n{gutter} |\n{line_number} | {line}\n{gutter} | {padding}{}\n",
+        token.kind,
+        "^".repeat(underlined),
+    )
+}
+
+pub fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r')
+}
+
+pub fn is_id_start(c: char) -> bool {
+    c == '_' || c.is_alphabetic()
+}
+
+pub fn is_id_continue(c: char) -> bool {
+    c == '_' || c.is_alphanumeric()
+}
+
+impl Cursor<'_> {
+    /// Consumes a single token and returns its kind, telling keywords from
+    /// identifiers with `keywords`. The cursor must not be empty.
+    fn eat_token(&mut self, keywords: &Keywords) -> TokenKind {
+        let token_start = self.remaining();
+        let Some(first_char) = self.adv() else {
+            return Eof;
+        };
+        match first_char {
+            c if is_whitespace(c) => self.eat_whitespace(),
+            'r' if matches!(self.peek(), '"' | '#') => self.eat_raw_str(),
+            'b' if matches!(self.peek(), '"' | '\'') => self.eat_byte_literal(),
+            c if is_id_start(c) => self.eat_id_continue(token_start, keywords),
+            c if c.is_ascii_digit() => self.eat_number(c),
+            '/' => match self.peek() {
+                '/' => self.eat_line_comment(),
+                '*' => self.eat_block_comment(),
+                _ if self.bump_if('=') => SlashEq,
+                _ => Slash,
+            },
+            '\'' => self.eat_lifetime_or_char(),
+            '"' => self.eat_double_quote_str(),
+            ';' => Semi,
+            '(' => OpenParen,
+            ')' => CloseParen,
+            '{' => OpenBrace,
+            '}' => CloseBrace,
+            '[' => OpenBracket,
+            ']' => CloseBracket,
+            ',' => Comma,
+            '.' if self.bump_if('.') => {
+                if self.bump_if('=') {
+                    DotDotEq
+                } else {
+                    DotDot
+                }
+            }
+            '.' => Dot,
+            '!' if self.bump_if('=') => Ne,
+            '!' => Exclam,
+            '+' if self.bump_if('=') => PlusEq,
+            '+' => Plus,
+            '-' if self.bump_if('>') => Arrow,
+            '-' if self.bump_if('=') => MinusEq,
+            '-' => Minus,
+            '=' if self.bump_if('>') => FatArrow,
+            '=' if self.bump_if('=') => EqEq,
+            '=' => Eq,
+            '<' if self.bump_if('=') => Le,
+            '<' if self.bump_if('<') => Shl,
+            '<' => Lt,
+            '>' if self.bump_if('=') => Ge,
+            '>' if self.bump_if('>') => Shr,
+            '>' => Gt,
+            '&' if self.bump_if('&') => AndAnd,
+            '&' => And,
+            '|' if self.bump_if('|') => OrOr,
+            '|' => Or,
+            '^' => Caret,
+            '?' => Question,
+            '#' => Pound,
+            '@' => At,
+            '$' => Dollar,
+            ':' if self.bump_if(':') => PathSep,
+            ':' => Colon,
+            '*' if self.bump_if('=') => StarEq,
+            '*' => Star,
+            '%' if self.bump_if('=') => PercentEq,
+            '%' => Percent,
+            _ => Unknown(first_char),
+        }
+    }
+
+    /// Eats a line comment whose first `/` has been consumed.
+    ///
+    /// `///` starts an outer doc comment, but `////` is an ordinary comment.
+    fn eat_line_comment(&mut self) -> TokenKind {
+        self.adv();
+        let doc = match (self.peek(), self.peek_second()) {
+            ('/', c) if c != '/' => Some(DocStyle::Outer),
+            ('!', _) => Some(DocStyle::Inner),
+            _ => None,
+        };
+        self.eat_rest_of_line();
+        LineComment {
+            kind: CommentKind { doc },
+        }
+    }
+
+    /// Eats a block comment whose first `/` has been consumed.
+    ///
+    /// `/**` starts an outer doc comment, but `/***` and `/**/` are ordinary
+    /// comments.
+    fn eat_block_comment(&mut self) -> TokenKind {
+        self.adv();
+        let doc = match (self.peek(), self.peek_second()) {
+            ('*', c) if c != '*' && c != '/' => Some(DocStyle::Outer),
+            ('!', _) => Some(DocStyle::Inner),
+            _ => None,
+        };
+        let mut depth = 1usize;
+        while let Some(c) = self.adv() {
+            match c {
+                '/' if self.bump_if('*') => depth += 1,
+                '*' if self.bump_if('/') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        BlockComment {
+            kind: CommentKind { doc },
+            terminated: depth == 0,
+        }
+    }
+
+    /// Eats up to, but not including, the next `\n` or `\r\n`.
+    fn eat_rest_of_line(&mut self) {
+        while !self.is_empty() {
+            match self.peek() {
+                '\n' => break,
+                '\r' if self.peek_second() == '\n' => break,
+                _ => {
+                    self.adv();
+                }
+            }
+        }
+    }
+
+    fn eat_whitespace(&mut self) -> TokenKind {
+        self.adv_until(is_whitespace);
+        Whitespace
+    }
+
+    /// Eats the rest of an identifier or keyword. `token_start` is the input
+    /// from the start of the token.
+    fn eat_id_continue(&mut self, token_start: &str, keywords: &Keywords) -> TokenKind {
+        self.adv_until(is_id_continue);
+        match keywords.lookup(&token_start[..self.len_consumed()]) {
+            Some(kw) => Keyword(kw),
+            None => Identifier,
+        }
+    }
+
+    /// Eats the rest of a number whose first digit has been consumed.
+    ///
+    /// A `.` only continues the number if it is not followed by another `.`
+    /// or an identifier, so `1..2` and `1.foo()` are not floats. Identifier
+    /// characters directly following the number (as in `123abc`) are treated
+    /// as part of the literal.
+    fn eat_number(&mut self, first_digit: char) -> TokenKind {
+        let mut base = Base::Decimal;
+        let mut empty_digits = false;
+        if first_digit == '0' {
+            let prefixed = match self.peek() {
+                'b' => Some(Base::Binary),
+                'o' => Some(Base::Octal),
+                'x' => Some(Base::Hexadecimal),
+                _ => None,
+            };
+            if let Some(prefixed) = prefixed {
+                base = prefixed;
+                self.adv();
+                empty_digits = !self.eat_digits(base);
+            }
+        }
+        if base == Base::Decimal {
+            self.eat_digits(base);
+        }
+        let empty_exponent = match self.peek() {
+            '.' if self.peek_second() != '.' && !is_id_start(self.peek_second()) => {
+                self.adv();
+                let mut empty_exponent = false;
+                if self.peek().is_ascii_digit() {
+                    self.eat_digits(Base::Decimal);
+                    if matches!(self.peek(), 'e' | 'E') {
+                        self.adv();
+                        empty_exponent = !self.eat_float_exponent();
+                    }
+                }
+                Some(empty_exponent)
+            }
+            'e' | 'E' if base != Base::Hexadecimal => {
+                self.adv();
+                Some(!self.eat_float_exponent())
+            }
+            _ => None,
+        };
+        let suffix_start = self.len_consumed();
+        self.adv_until(is_id_continue);
+        let kind = match empty_exponent {
+            Some(empty_exponent) => Float {
+                base,
+                empty_exponent,
+                suffix_start,
+            },
+            None => Int {
+                base,
+                empty_digits,
+                suffix_start,
+            },
+        };
+        Literal { kind }
+    }
+
+    /// Eats the exponent of a float after the `e`, returning whether it had
+    /// any digits.
+    fn eat_float_exponent(&mut self) -> bool {
+        if matches!(self.peek(), '+' | '-') {
+            self.adv();
+        }
+        self.eat_digits(Base::Decimal)
+    }
+
+    /// Eats digits valid in `base` along with any `_` separators, returning
+    /// whether there were any digits.
+    fn eat_digits(&mut self, base: Base) -> bool {
+        let mut has_digits = false;
+        self.adv_until(|c| {
+            let is_digit = c.is_digit(base.radix());
+            has_digits |= is_digit;
+            is_digit || c == '_'
+        });
+        has_digits
+    }
+
+    /// Eats a lifetime or a char literal whose opening `'` has been consumed.
+    ///
+    /// `'a` is a lifetime unless the identifier is followed by a closing
+    /// quote, as in `'a'`.
+    fn eat_lifetime_or_char(&mut self) -> TokenKind {
+        let can_be_lifetime = self.peek_second() != '\'' && is_id_start(self.peek());
+        if !can_be_lifetime {
+            let terminated = self.eat_single_quote_str();
+            return Literal {
+                kind: Char { terminated },
+            };
+        }
+        self.adv();
+        self.adv_until(is_id_continue);
+        if self.bump_if('\'') {
+            Literal {
+                kind: Char { terminated: true },
+            }
+        } else {
+            Lifetime
+        }
+    }
+
+    /// Eats the rest of a char literal, returning whether it was terminated.
+    ///
+    /// A char literal does not span lines, so an unterminated one stops at
+    /// the end of the line.
+    fn eat_single_quote_str(&mut self) -> bool {
+        loop {
+            match self.peek() {
+                '\'' => {
+                    self.adv();
+                    return true;
+                }
+                '\\' => {
+                    self.adv_n(2);
+                }
+                '\n' => return false,
+                _ if self.is_empty() => return false,
+                _ => {
+                    self.adv();
+                }
+            }
+        }
+    }
+
+    /// Eats a raw string literal whose `r` has been consumed.
+    ///
+    /// The string ends at the first `"` followed by as many `#`s as there
+    /// were before the opening quote.
+    fn eat_raw_str(&mut self) -> TokenKind {
+        let n_hashes = self.eat_while(|c| c == '#');
+        let terminated = self.bump_if('"') && {
+            loop {
+                match self.adv() {
+                    Some('"') => {
+                        let mut closing = 0;
+                        while closing < n_hashes && self.bump_if('#') {
+                            closing += 1;
+                        }
+                        if closing == n_hashes {
+                            break true;
+                        }
+                    }
+                    Some(_) => {}
+                    None => break false,
+                }
+            }
+        };
+        Literal {
+            kind: RawStr {
+                n_hashes: u8::try_from(n_hashes).unwrap_or(u8::MAX),
+                terminated,
+            },
+        }
+    }
+
+    /// Eats a string literal whose opening `"` has been consumed.
+    fn eat_double_quote_str(&mut self) -> TokenKind {
+        let (terminated, has_invalid_escape) = self.eat_str_body();
+        Literal {
+            kind: Str {
+                terminated,
+                has_invalid_escape,
+            },
+        }
+    }
+
+    /// Eats everything up to and including the closing `"` of a string,
+    /// returning whether it was terminated and whether it contained an
+    /// invalid escape.
+    fn eat_str_body(&mut self) -> (bool, bool) {
+        let mut has_invalid_escape = false;
+        let terminated = loop {
+            match self.adv() {
+                Some('"') => break true,
+                Some('\\') => has_invalid_escape |= !self.eat_escape(),
+                Some(_) => {}
+                None => break false,
+            }
+        };
+        (terminated, has_invalid_escape)
+    }
+
+    /// Eats a byte or byte string literal whose `b` has been consumed.
+    fn eat_byte_literal(&mut self) -> TokenKind {
+        let kind = match self.adv() {
+            Some('\'') => Byte {
+                terminated: self.eat_single_quote_str(),
+            },
+            _ => ByteStr {
+                terminated: self.eat_str_body().0,
+            },
+        };
+        Literal { kind }
+    }
+
+    /// Eats the escape after a `\`, returning whether it is valid. Only
+    /// `\u{...}` escapes have their contents checked.
+    fn eat_escape(&mut self) -> bool {
+        match self.adv() {
+            Some('n' | 'r' | 't' | '\\' | '0' | '\'' | '"' | 'x' | '\n') => true,
+            Some('u') => self.eat_unicode_escape(),
+            _ => false,
+        }
+    }
+
+    /// Eats the `{...}` of a `\u{...}` escape, returning whether it holds 1
+    /// to 6 hex digits naming a Unicode scalar value.
+    fn eat_unicode_escape(&mut self) -> bool {
+        if !self.bump_if('{') {
+            return false;
+        }
+        let digits = self.remaining();
+        let n_digits = self.eat_while(|c| c.is_ascii_hexdigit());
+        if !self.bump_if('}') || !(1..=6).contains(&n_digits) {
+            return false;
+        }
+        u32::from_str_radix(&digits[..n_digits], 16)
+            .ok()
+            .and_then(char::from_u32)
+            .is_some()
+    }
+}
@@ -0,0 +1,394 @@
+use std::str::Chars;
+
+/// Returned by [`Cursor::peek`] when there are no characters left.
+///
+/// A `\0` in the input peeks the same, so the end of input has to be
+/// detected with [`Cursor::is_empty`] instead.
+pub const EOF_CHAR: char = '\0';
+
+/// Peekable iterator over the characters of some input.
+///
+/// The cursor remembers how much of the input it has consumed since the last
+/// call to [`Cursor::reset_len_consumed`], which the lexer uses to compute
+/// token lengths. All lengths and offsets are in bytes, so a multi-byte
+/// character such as `é` counts for more than one.
+pub struct Cursor<'a> {
+    /// The whole input, kept for [`Cursor::reset`].
+    input: &'a str,
+    /// Bytes left in the input at the last [`Cursor::reset_len_consumed`].
+    initial_len: usize,
+    /// Characters consumed since the last [`Cursor::reset_len_consumed`].
+    chars_consumed: usize,
+    chars: Chars<'a>,
+    /// The last character consumed, or [`EOF_CHAR`] before the first.
+    prev: char,
+}
+
+/// A saved position of a [`Cursor`], see [`Cursor::checkpoint`].
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint<'a> {
+    remaining: &'a str,
+    chars_consumed: usize,
+    prev: char,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(input: &'a str) -> Cursor<'a> {
+        Cursor {
+            input,
+            initial_len: input.len(),
+            chars_consumed: 0,
+            chars: input.chars(),
+            prev: EOF_CHAR,
+        }
+    }
+
+    /// Returns the next character without consuming it, or [`EOF_CHAR`] if
+    /// the input is exhausted.
+    pub fn peek(&self) -> char {
+        self.peek_nth(0)
+    }
+
+    /// Returns the character after the next one without consuming anything,
+    /// or [`EOF_CHAR`] if there are fewer than two characters left.
+    pub fn peek_second(&self) -> char {
+        self.peek_nth(1)
+    }
+
+    /// Returns the character `n` places ahead without consuming anything, so
+    /// `peek_nth(0)` is the next character. Returns [`EOF_CHAR`] past the end
+    /// of the input.
+    ///
+    /// This walks a copy of the underlying iterator, so it costs O(n).
+    pub fn peek_nth(&self, n: usize) -> char {
+        self.chars.clone().nth(n).unwrap_or(EOF_CHAR)
+    }
+
+    /// The part of the input that has not been consumed yet.
+    ///
+    /// The slice is a snapshot: it stops describing the remaining input as
+    /// soon as the cursor advances again.
+    pub fn remaining(&self) -> &'a str {
+        self.chars.as_str()
+    }
+
+    /// Whether all of the input has been consumed.
+    pub fn is_empty(&self) -> bool {
+        self.chars.as_str().is_empty()
+    }
+
+    /// Byte offset of the next character in the input.
+    pub fn pos(&self) -> usize {
+        self.input.len() - self.chars.as_str().len()
+    }
+
+    /// Number of bytes consumed since the last [`Cursor::reset_len_consumed`].
+    pub fn len_consumed(&self) -> usize {
+        self.initial_len - self.chars.as_str().len()
+    }
+
+    /// Number of characters consumed since the last
+    /// [`Cursor::reset_len_consumed`]. This differs from
+    /// [`Cursor::len_consumed`] once a multi-byte character is consumed.
+    pub fn chars_consumed(&self) -> usize {
+        self.chars_consumed
+    }
+
+    /// Starts counting consumed bytes and characters from the current
+    /// position.
+    pub fn reset_len_consumed(&mut self) {
+        self.initial_len = self.chars.as_str().len();
+        self.chars_consumed = 0;
+    }
+
+    /// Rewinds the cursor to the start of its input, as if it had just been
+    /// created.
+    pub fn reset(&mut self) {
+        *self = Cursor::new(self.input);
+    }
+
+    /// Saves the current position so [`Cursor::restore`] can go back to it.
+    pub fn checkpoint(&self) -> Checkpoint<'a> {
+        Checkpoint {
+            remaining: self.chars.as_str(),
+            chars_consumed: self.chars_consumed,
+            prev: self.prev,
+        }
+    }
+
+    /// Moves back to a position saved by [`Cursor::checkpoint`] on this
+    /// cursor, undoing everything consumed since.
+    pub fn restore(&mut self, checkpoint: Checkpoint<'a>) {
+        self.chars = checkpoint.remaining.chars();
+        self.chars_consumed = checkpoint.chars_consumed;
+        self.prev = checkpoint.prev;
+    }
+
+    /// Returns the last character consumed, or [`EOF_CHAR`] if nothing has
+    /// been consumed yet.
+    pub fn prev(&self) -> char {
+        self.prev
+    }
+
+    /// Consumes and returns the next character.
+    pub fn adv(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.prev = c;
+        self.chars_consumed += 1;
+        Some(c)
+    }
+
+    /// Consumes the next `n` characters, returning how many there were, which
+    /// is fewer than `n` at the end of the input.
+    pub fn adv_n(&mut self, n: usize) -> usize {
+        let mut advanced = 0;
+        while advanced < n && self.adv().is_some() {
+            advanced += 1;
+        }
+        advanced
+    }
+
+    /// Consumes the next character if it is `expected`, returning whether it
+    /// did.
+    pub fn bump_if(&mut self, expected: char) -> bool {
+        let matches = !self.is_empty() && self.peek() == expected;
+        if matches {
+            self.adv();
+        }
+        matches
+    }
+
+    /// Advances for as long as `predicate` holds for the next character.
+    pub fn adv_until(&mut self, predicate: impl FnMut(char) -> bool) {
+        self.eat_while(predicate);
+    }
+
+    /// Advances for as long as `predicate` holds for the next character and
+    /// the number of characters this call has already consumed, so the first
+    /// character is checked with index 0.
+    pub fn adv_while(&mut self, mut predicate: impl FnMut(char, usize) -> bool) {
+        let mut n = 0;
+        self.adv_until(|c| {
+            let keep_going = predicate(c, n);
+            n += 1;
+            keep_going
+        });
+    }
+
+    /// Like [`Cursor::adv_until`], but returns the number of characters
+    /// consumed.
+    pub fn eat_while(&mut self, mut predicate: impl FnMut(char) -> bool) -> usize {
+        let mut n = 0;
+        while !self.is_empty() && predicate(self.peek()) {
+            self.adv();
+            n += 1;
+        }
+        n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peek_does_not_consume() {
+        let cursor = Cursor::new("ab");
+        assert_eq!(cursor.peek(), 'a');
+        assert_eq!(cursor.peek(), 'a');
+        assert_eq!(cursor.len_consumed(), 0);
+    }
+
+    #[test]
+    fn peek_at_end() {
+        let cursor = Cursor::new("");
+        assert_eq!(cursor.peek(), EOF_CHAR);
+        assert!(cursor.is_empty());
+    }
+
+    #[test]
+    fn peek_second() {
+        let mut cursor = Cursor::new("ab");
+        assert_eq!(cursor.peek_second(), 'b');
+        cursor.adv();
+        assert_eq!(cursor.peek_second(), EOF_CHAR);
+        assert_eq!(Cursor::new("").peek_second(), EOF_CHAR);
+    }
+
+    #[test]
+    fn eat_while_counts_chars() {
+        let mut cursor = Cursor::new("aaab");
+        assert_eq!(cursor.eat_while(|c| c == 'a'), 3);
+        assert_eq!(cursor.eat_while(|c| c == 'a'), 0);
+        assert_eq!(cursor.peek(), 'b');
+    }
+
+    #[test]
+    fn peek_nth() {
+        let cursor = Cursor::new("abc");
+        assert_eq!(cursor.peek_nth(0), 'a');
+        assert_eq!(cursor.peek_nth(2), 'c');
+        assert_eq!(cursor.peek_nth(3), EOF_CHAR);
+        assert_eq!(cursor.len_consumed(), 0);
+    }
+
+    #[test]
+    fn remaining_shrinks() {
+        let mut cursor = Cursor::new("abc");
+        assert_eq!(cursor.remaining(), "abc");
+        cursor.adv();
+        assert_eq!(cursor.remaining(), "bc");
+        cursor.adv();
+        cursor.adv();
+        assert_eq!(cursor.remaining(), "");
+    }
+
+    #[test]
+    fn prev() {
+        let mut cursor = Cursor::new("ab");
+        assert_eq!(cursor.prev(), '\0');
+        cursor.adv();
+        cursor.adv();
+        assert_eq!(cursor.prev(), 'b');
+        cursor.adv();
+        assert_eq!(cursor.prev(), 'b');
+    }
+
+    #[test]
+    fn len_consumed_and_reset() {
+        let mut cursor = Cursor::new("abc");
+        cursor.adv();
+        cursor.adv();
+        assert_eq!(cursor.len_consumed(), 2);
+        cursor.reset_len_consumed();
+        assert_eq!(cursor.len_consumed(), 0);
+        assert_eq!(cursor.adv(), Some('c'));
+        assert_eq!(cursor.len_consumed(), 1);
+        assert_eq!(cursor.adv(), None);
+    }
+
+    #[test]
+    fn adv_n() {
+        let mut cursor = Cursor::new("abcde");
+        assert_eq!(cursor.adv_n(3), 3);
+        assert_eq!(cursor.peek(), 'd');
+        assert_eq!(cursor.adv_n(0), 0);
+
+        let mut cursor = Cursor::new("ab");
+        assert_eq!(cursor.adv_n(10), 2);
+        assert!(cursor.is_empty());
+        assert_eq!(cursor.prev(), 'b');
+    }
+
+    #[test]
+    fn bump_if() {
+        let mut cursor = Cursor::new("::");
+        assert!(cursor.bump_if(':'));
+        assert_eq!(cursor.len_consumed(), 1);
+        assert!(!cursor.bump_if('>'));
+        assert_eq!(cursor.len_consumed(), 1);
+        assert!(cursor.bump_if(':'));
+        assert!(!cursor.bump_if(':'));
+        assert!(!cursor.bump_if(EOF_CHAR));
+    }
+
+    #[test]
+    fn adv_while_passes_index() {
+        use crate::lexer::{is_id_continue, is_id_start};
+
+        let mut cursor = Cursor::new("a1b 2");
+        cursor.adv_while(|c, i| {
+            if i == 0 {
+                is_id_start(c)
+            } else {
+                is_id_continue(c)
+            }
+        });
+        assert_eq!(cursor.len_consumed(), 3);
+
+        let mut cursor = Cursor::new("1ab");
+        cursor.adv_while(|c, i| {
+            if i == 0 {
+                is_id_start(c)
+            } else {
+                is_id_continue(c)
+            }
+        });
+        assert_eq!(cursor.len_consumed(), 0);
+    }
+
+    #[test]
+    fn reset() {
+        let mut cursor = Cursor::new("abc");
+        cursor.adv();
+        cursor.reset_len_consumed();
+        cursor.adv();
+        cursor.reset();
+        assert_eq!(cursor.prev(), EOF_CHAR);
+        assert_eq!(cursor.len_consumed(), 0);
+        assert_eq!(cursor.pos(), 0);
+        assert_eq!(cursor.adv(), Some('a'));
+        assert_eq!(cursor.remaining(), "bc");
+    }
+
+    #[test]
+    fn lengths_are_in_bytes() {
+        let mut cursor = Cursor::new("é!");
+        assert_eq!(cursor.adv(), Some('é'));
+        assert_eq!(cursor.len_consumed(), 2);
+        assert_eq!(cursor.pos(), 2);
+        cursor.reset_len_consumed();
+        cursor.adv();
+        assert_eq!(cursor.len_consumed(), 1);
+        assert_eq!(cursor.pos(), 3);
+    }
+
+    #[test]
+    fn chars_consumed() {
+        let mut cursor = Cursor::new("aé€b");
+        cursor.adv();
+        cursor.adv();
+        cursor.adv();
+        assert_eq!(cursor.chars_consumed(), 3);
+        assert_eq!(cursor.len_consumed(), 6);
+        cursor.reset_len_consumed();
+        assert_eq!(cursor.chars_consumed(), 0);
+        cursor.adv();
+        cursor.adv();
+        assert_eq!(cursor.chars_consumed(), 1);
+    }
+
+    #[test]
+    fn adv_until_stops_at_predicate() {
+        let mut cursor = Cursor::new("aaab");
+        cursor.adv_until(|c| c == 'a');
+        assert_eq!(cursor.len_consumed(), 3);
+        assert_eq!(cursor.peek(), 'b');
+    }
+
+    #[test]
+    fn checkpoint_and_restore() {
+        let mut cursor = Cursor::new("abcdé");
+        cursor.adv();
+        let checkpoint = cursor.checkpoint();
+        cursor.adv_n(4);
+        assert!(cursor.is_empty());
+        cursor.restore(checkpoint);
+        assert_eq!(cursor.pos(), 1);
+        assert_eq!(cursor.len_consumed(), 1);
+        assert_eq!(cursor.chars_consumed(), 1);
+        assert_eq!(cursor.prev(), 'a');
+        assert_eq!(cursor.remaining(), "bcdé");
+    }
+
+    #[test]
+    fn null_is_not_end_of_input() {
+        let mut cursor = Cursor::new("\0\0a");
+        assert_eq!(cursor.peek(), EOF_CHAR);
+        assert!(!cursor.is_empty());
+        assert!(cursor.bump_if('\0'));
+        cursor.adv_until(|c| c == '\0');
+        assert_eq!(cursor.peek(), 'a');
+        assert_eq!(cursor.len_consumed(), 2);
+    }
+}
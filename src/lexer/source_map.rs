@@ -0,0 +1,139 @@
+//! Several source files lexed as one, with spans into their concatenation.
+
+use super::{tokenize, Span, Token};
+
+/// Identifies a file added to a [`SourceMap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+/// Source files laid out end to end, so that one byte offset, and so one
+/// [`Span`], can point into any of them.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+#[derive(Debug)]
+struct SourceFile {
+    name: String,
+    src: String,
+    /// Global offset of the file's first byte.
+    start: usize,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// Adds a file after all the others.
+    pub fn add_file(&mut self, name: impl Into<String>, src: impl Into<String>) -> FileId {
+        let id = FileId(u32::try_from(self.files.len()).expect("too many files"));
+        let start = self
+            .files
+            .last()
+            .map_or(0, |file| file.start + file.src.len());
+        self.files.push(SourceFile {
+            name: name.into(),
+            src: src.into(),
+            start,
+        });
+        id
+    }
+
+    pub fn name(&self, id: FileId) -> &str {
+        &self.file(id).name
+    }
+
+    pub fn source(&self, id: FileId) -> &str {
+        &self.file(id).src
+    }
+
+    /// The file containing the byte at `global_offset`, and the offset of
+    /// that byte within it. The end of the last file belongs to that file.
+    ///
+    /// # Panics
+    ///
+    /// If `global_offset` is past the end of the last file.
+    pub fn lookup(&self, global_offset: usize) -> (FileId, usize) {
+        let end = self
+            .files
+            .last()
+            .map_or(0, |file| file.start + file.src.len());
+        assert!(
+            global_offset <= end && !self.files.is_empty(),
+            "offset {global_offset} is outside the source map"
+        );
+        // Empty files share their start with the next file, which wins.
+        let index = self
+            .files
+            .partition_point(|file| file.start <= global_offset)
+            - 1;
+        (
+            FileId(index as u32),
+            global_offset - self.files[index].start,
+        )
+    }
+
+    /// Lexes the file `id`, with spans offset to point into the whole map.
+    /// Token locations stay relative to the file.
+    pub fn tokenize_file(&self, id: FileId) -> impl Iterator<Item = Token> + '_ {
+        let file = self.file(id);
+        tokenize(&file.src).map(move |token| {
+            let span = Span {
+                lo: file.start + token.span.lo,
+                hi: file.start + token.span.hi,
+            };
+            Token::new(token.kind, span, token.location)
+        })
+    }
+
+    fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0 as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::TokenKind;
+
+    #[test]
+    fn spans_resolve_to_their_file() {
+        let mut map = SourceMap::new();
+        let a = map.add_file("a.rc", "fn a() {}\n");
+        let b = map.add_file("b.rc", "let x = y;");
+        assert_eq!(map.name(b), "b.rc");
+
+        let tokens: Vec<Token> = map.tokenize_file(b).collect();
+        let y = tokens[6];
+        assert_eq!(y.kind, TokenKind::Identifier);
+        assert_eq!(y.span, Span { lo: 18, hi: 19 });
+        assert_eq!(map.lookup(y.span.lo), (b, 8));
+        assert_eq!(y.location.col, 9);
+        assert_eq!(&map.source(b)[8..9], "y");
+
+        let first = map.tokenize_file(a).next().unwrap();
+        assert_eq!(map.lookup(first.span.lo), (a, 0));
+        assert_eq!(map.lookup(9), (a, 9));
+        assert_eq!(map.lookup(10), (b, 0));
+        assert_eq!(map.lookup(20), (b, 10));
+    }
+
+    #[test]
+    fn empty_files() {
+        let mut map = SourceMap::new();
+        map.add_file("empty.rc", "");
+        let b = map.add_file("b.rc", "x");
+        assert_eq!(map.lookup(0), (b, 0));
+        assert_eq!(map.tokenize_file(FileId(0)).count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn lookup_past_end() {
+        let mut map = SourceMap::new();
+        map.add_file("a.rc", "x");
+        map.lookup(2);
+    }
+}
@@ -0,0 +1,1292 @@
+use super::*;
+
+fn kinds(input: &str) -> Vec<TokenKind> {
+    tokenize(input).map(|t| t.kind).collect()
+}
+
+fn lex(input: &str) -> Vec<(TokenKind, usize)> {
+    tokenize(input).map(|t| (t.kind, t.len)).collect()
+}
+
+/// A decimal integer literal whose suffix, if any, starts at `suffix_start`.
+const fn dec(suffix_start: usize) -> TokenKind {
+    int(Base::Decimal, false, suffix_start)
+}
+
+const fn int(base: Base, empty_digits: bool, suffix_start: usize) -> TokenKind {
+    Literal {
+        kind: Int {
+            base,
+            empty_digits,
+            suffix_start,
+        },
+    }
+}
+
+const fn float(suffix_start: usize) -> TokenKind {
+    Literal {
+        kind: Float {
+            base: Base::Decimal,
+            empty_exponent: false,
+            suffix_start,
+        },
+    }
+}
+
+const LINE_COMMENT: TokenKind = LineComment {
+    kind: CommentKind { doc: None },
+};
+
+const fn block(terminated: bool) -> TokenKind {
+    BlockComment {
+        kind: CommentKind { doc: None },
+        terminated,
+    }
+}
+
+const fn str_lit(terminated: bool, has_invalid_escape: bool) -> TokenKind {
+    Literal {
+        kind: Str {
+            terminated,
+            has_invalid_escape,
+        },
+    }
+}
+
+#[test]
+fn fn_main() {
+    let input = "fn main() {\n    println!(\"hello world\");\n}\n";
+    let tokens: Vec<Token> = tokenize(input).collect();
+    let expected = [
+        (Keyword(Keyword::Fn), "fn"),
+        (Whitespace, " "),
+        (Identifier, "main"),
+        (OpenParen, "("),
+        (CloseParen, ")"),
+        (Whitespace, " "),
+        (OpenBrace, "{"),
+        (Whitespace, "\n    "),
+        (Identifier, "println"),
+        (Exclam, "!"),
+        (OpenParen, "("),
+        (str_lit(true, false), "\"hello world\""),
+        (CloseParen, ")"),
+        (Semi, ";"),
+        (Whitespace, "\n"),
+        (CloseBrace, "}"),
+        (Whitespace, "\n"),
+    ];
+    assert_eq!(tokens.len(), expected.len());
+    for (token, (kind, text)) in tokens.iter().zip(expected) {
+        assert_eq!(token.kind, kind);
+        assert_eq!(token.len, text.len());
+        assert_eq!(token.text(input), text);
+    }
+}
+
+#[test]
+fn token_text() {
+    let input = "fn main() {}";
+    let tokens = tokenize_to_vec(input);
+    assert_eq!(tokens[2].text(input), "main");
+    assert_eq!(tokens[3].text(input), "(");
+}
+
+#[test]
+fn spans_are_contiguous() {
+    let input = "let x = \"a\"; // done";
+    let tokens: Vec<Token> = tokenize(input).collect();
+    assert_eq!(tokens[0].span, Span { lo: 0, hi: 3 });
+    for pair in tokens.windows(2) {
+        assert_eq!(pair[0].span.hi, pair[1].span.lo);
+    }
+    assert_eq!(tokens.last().unwrap().span.hi, input.len());
+}
+
+#[test]
+fn locations() {
+    let input = "fn main() {\n\tx;\n}";
+    let tokens: Vec<Token> = tokenize(input).collect();
+    assert_eq!(tokens[0].location(), Location { line: 1, col: 1 });
+    assert_eq!(tokens[2].location(), Location { line: 1, col: 4 });
+    // `x` follows a newline and a tab, which counts as a single column.
+    assert_eq!(tokens[8].kind, Identifier);
+    assert_eq!(tokens[8].location(), Location { line: 2, col: 2 });
+    assert_eq!(
+        tokens.last().unwrap().location(),
+        Location { line: 3, col: 1 }
+    );
+}
+
+#[test]
+fn location_after_multiline_token() {
+    let tokens: Vec<Token> = tokenize("/* a\nb */ c").collect();
+    assert_eq!(tokens[2].location(), Location { line: 2, col: 6 });
+}
+
+#[test]
+fn unterminated_str() {
+    assert_eq!(kinds("\"abc"), [str_lit(false, false)]);
+}
+
+#[test]
+fn unknown_char() {
+    assert_eq!(kinds("~"), [Unknown('~')]);
+    assert_eq!(kinds("\u{a7}"), [Unknown('\u{a7}')]);
+}
+
+#[test]
+fn int_zero() {
+    assert_eq!(kinds("0"), [dec(1)]);
+}
+
+#[test]
+fn int_decimal() {
+    let tokens = lex("42;");
+    assert_eq!(tokens[0], (dec(2), 2));
+    assert_eq!(tokens[1].0, Semi);
+}
+
+#[test]
+fn int_at_end_of_input() {
+    let tokens = lex("x 123");
+    assert_eq!(tokens.last(), Some(&(dec(3), 3)));
+}
+
+#[test]
+fn int_followed_by_ident_chars() {
+    assert_eq!(lex("123abc"), [(dec(3), 6)]);
+}
+
+#[test]
+fn int_hexadecimal() {
+    assert_eq!(lex("0xFF"), [(int(Base::Hexadecimal, false, 4), 4)]);
+}
+
+#[test]
+fn int_binary() {
+    assert_eq!(lex("0b1010"), [(int(Base::Binary, false, 6), 6)]);
+}
+
+#[test]
+fn int_octal() {
+    assert_eq!(lex("0o777"), [(int(Base::Octal, false, 5), 5)]);
+}
+
+#[test]
+fn int_prefix_without_digits() {
+    assert_eq!(
+        lex("0x;"),
+        [(int(Base::Hexadecimal, true, 2), 2), (Semi, 1)]
+    );
+}
+
+#[test]
+fn float_with_fraction() {
+    assert_eq!(lex("3.14"), [(float(4), 4)]);
+    assert_eq!(lex("1.0"), [(float(3), 3)]);
+}
+
+#[test]
+fn float_trailing_dot() {
+    assert_eq!(lex("1."), [(float(2), 2)]);
+}
+
+#[test]
+fn float_exponent() {
+    assert_eq!(lex("1e5"), [(float(3), 3)]);
+    assert_eq!(lex("2.5e10"), [(float(6), 6)]);
+    assert_eq!(lex("2E-3"), [(float(4), 4)]);
+}
+
+#[test]
+fn float_empty_exponent() {
+    assert_eq!(
+        kinds("1e;"),
+        [
+            Literal {
+                kind: Float {
+                    base: Base::Decimal,
+                    empty_exponent: true,
+                    suffix_start: 2,
+                }
+            },
+            Semi
+        ]
+    );
+}
+
+#[test]
+fn int_followed_by_field_access() {
+    let tokens = lex("1.foo");
+    assert_eq!(tokens[0], (dec(1), 1));
+    assert_eq!(tokens[1].1, 1);
+    assert_eq!(tokens[2], (Identifier, 3));
+}
+
+#[test]
+fn int_followed_by_range() {
+    let tokens = lex("1..2");
+    assert_eq!(tokens[0], (dec(1), 1));
+    assert_eq!(tokens.last(), Some(&(dec(1), 1)));
+}
+
+#[test]
+fn char_literal() {
+    assert_eq!(
+        lex("'x'"),
+        [(
+            Literal {
+                kind: Char { terminated: true }
+            },
+            3
+        )]
+    );
+}
+
+#[test]
+fn char_escaped_quote() {
+    assert_eq!(
+        lex(r"'\''"),
+        [(
+            Literal {
+                kind: Char { terminated: true }
+            },
+            4
+        )]
+    );
+}
+
+#[test]
+fn char_unterminated() {
+    assert_eq!(
+        kinds("' \n"),
+        [
+            Literal {
+                kind: Char { terminated: false }
+            },
+            Whitespace
+        ]
+    );
+}
+
+#[test]
+fn lifetime() {
+    assert_eq!(lex("'a"), [(Lifetime, 2)]);
+    assert_eq!(kinds("'a b"), [Lifetime, Whitespace, Identifier]);
+}
+
+#[test]
+fn unterminated_char_vs_lifetime() {
+    // `'x` on its own cannot be told apart from a lifetime.
+    assert_eq!(kinds("'x"), [Lifetime]);
+    assert_eq!(
+        kinds("'1"),
+        [Literal {
+            kind: Char { terminated: false }
+        }]
+    );
+}
+
+#[test]
+fn line_comment() {
+    assert_eq!(lex("// hello\n"), [(LINE_COMMENT, 8), (Whitespace, 1)]);
+}
+
+#[test]
+fn line_comment_at_end_of_input() {
+    assert_eq!(lex("x // hello").last(), Some(&(LINE_COMMENT, 8)));
+}
+
+#[test]
+fn lone_slash() {
+    assert_eq!(kinds("/"), [Slash]);
+}
+
+#[test]
+fn block_comment() {
+    assert_eq!(lex("/**/x"), [(block(true), 4), (Identifier, 1)]);
+}
+
+#[test]
+fn block_comment_nested() {
+    let input = "/* outer /* inner */ still open */";
+    assert_eq!(lex(input), [(block(true), input.len())]);
+}
+
+#[test]
+fn block_comment_unterminated() {
+    let input = "/* outer /* inner */";
+    assert_eq!(lex(input), [(block(false), input.len())]);
+}
+
+#[test]
+fn arithmetic_operators() {
+    let significant: Vec<TokenKind> = kinds("1 + 2 * 3")
+        .into_iter()
+        .filter(|&k| k != Whitespace)
+        .collect();
+    assert_eq!(significant, [dec(1), Plus, dec(1), Star, dec(1)]);
+    assert_eq!(kinds("-%/"), [Minus, Percent, Slash]);
+}
+
+#[test]
+fn compound_punctuation() {
+    assert_eq!(lex("->"), [(Arrow, 2)]);
+    assert_eq!(lex("=>"), [(FatArrow, 2)]);
+    assert_eq!(lex("::"), [(PathSep, 2)]);
+    assert_eq!(lex(":"), [(Colon, 1)]);
+    assert_eq!(lex("="), [(Eq, 1)]);
+}
+
+#[test]
+fn minus_at_end_of_input() {
+    assert_eq!(lex("-"), [(Minus, 1)]);
+    assert_eq!(kinds("a:::b"), [Identifier, PathSep, Colon, Identifier]);
+}
+
+#[test]
+fn keywords() {
+    assert_eq!(lex("fn"), [(Keyword(Keyword::Fn), 2)]);
+    assert_eq!(lex("fna"), [(Identifier, 3)]);
+    assert_eq!(
+        kinds("let x = true"),
+        [
+            Keyword(Keyword::Let),
+            Whitespace,
+            Identifier,
+            Whitespace,
+            Eq,
+            Whitespace,
+            Keyword(Keyword::True)
+        ]
+    );
+}
+
+#[test]
+fn str_escaped_quote() {
+    assert_eq!(lex(r#""a\"b""#), [(str_lit(true, false), 6)]);
+}
+
+#[test]
+fn str_escaped_newline() {
+    assert_eq!(lex(r#""line\n""#), [(str_lit(true, false), 8)]);
+}
+
+#[test]
+fn str_invalid_escape() {
+    assert_eq!(lex(r#""bad\q""#), [(str_lit(true, true), 7)]);
+}
+
+#[test]
+fn str_unicode_escapes() {
+    assert_eq!(lex(r#""\u{1F600}""#), [(str_lit(true, false), 11)]);
+    assert_eq!(lex(r#""\u{0}\u{10FFFF}""#), [(str_lit(true, false), 17)]);
+    assert_eq!(lex(r#""\u{}""#), [(str_lit(true, true), 6)]);
+    assert_eq!(lex(r#""\u{110000}""#), [(str_lit(true, true), 12)]);
+    assert_eq!(lex(r#""\u{D800}""#), [(str_lit(true, true), 10)]);
+    assert_eq!(lex(r#""\u{1234567}""#), [(str_lit(true, true), 13)]);
+    assert_eq!(lex(r#""\u1234""#), [(str_lit(true, true), 8)]);
+    assert_eq!(lex(r#""\u{12""#), [(str_lit(true, true), 7)]);
+}
+
+#[test]
+fn str_trailing_backslash() {
+    assert_eq!(lex(r#""\"#), [(str_lit(false, true), 2)]);
+}
+
+const fn raw_str(n_hashes: u8, terminated: bool) -> TokenKind {
+    Literal {
+        kind: RawStr {
+            n_hashes,
+            terminated,
+        },
+    }
+}
+
+#[test]
+fn raw_str_plain() {
+    assert_eq!(lex(r#"r"x""#), [(raw_str(0, true), 4)]);
+    assert_eq!(lex(r#"r"a\"b"#), [(raw_str(0, true), 5), (Identifier, 1)]);
+}
+
+#[test]
+fn raw_str_with_hashes() {
+    assert_eq!(lex(r##"r#"a"b"#"##), [(raw_str(1, true), 8)]);
+}
+
+#[test]
+fn raw_str_mismatched_inner_hashes() {
+    assert_eq!(lex(r###"r##"a"#"##"###), [(raw_str(2, true), 10)]);
+}
+
+#[test]
+fn raw_str_unterminated() {
+    assert_eq!(lex(r##"r#"abc"##), [(raw_str(1, false), 6)]);
+    assert_eq!(lex("r#x"), [(raw_str(1, false), 2), (Identifier, 1)]);
+}
+
+#[test]
+fn r_identifier() {
+    assert_eq!(
+        lex("r rx"),
+        [(Identifier, 1), (Whitespace, 1), (Identifier, 2)]
+    );
+}
+
+#[test]
+fn eof_token() {
+    let mut tokens = tokenize_with_eof("a\nb");
+    assert_eq!(tokens.by_ref().map(|t| t.kind).take(3).count(), 3);
+    let eof = tokens.next().unwrap();
+    assert_eq!(eof.kind, Eof);
+    assert_eq!(eof.len, 0);
+    assert_eq!(eof.span, Span { lo: 3, hi: 3 });
+    assert_eq!(eof.location(), Location { line: 2, col: 2 });
+    assert_eq!(tokens.next(), None);
+    assert_eq!(tokens.next(), None);
+}
+
+#[test]
+fn eof_token_empty_input() {
+    let tokens: Vec<Token> = tokenize_with_eof("").collect();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].kind, Eof);
+    assert_eq!(tokens[0].location(), Location::default());
+}
+
+#[test]
+fn display_token_kind() {
+    assert_eq!(Whitespace.to_string(), "whitespace");
+    assert_eq!(Identifier.to_string(), "identifier");
+    assert_eq!(OpenParen.to_string(), "(");
+    assert_eq!(CloseParen.to_string(), ")");
+    assert_eq!(Semi.to_string(), ";");
+    assert_eq!(Keyword(Keyword::Fn).to_string(), "keyword `fn`");
+    assert_eq!(
+        str_lit(true, false).to_string(),
+        "string literal (terminated)"
+    );
+    assert_eq!(
+        int(Base::Hexadecimal, false, 4).to_string(),
+        "hexadecimal integer literal"
+    );
+    assert_eq!(block(false).to_string(), "block comment (unterminated)");
+}
+
+#[test]
+fn try_tokenize_ok() {
+    let input = "fn main() { x; }";
+    assert_eq!(try_tokenize(input), Ok(tokenize(input).collect()));
+}
+
+#[test]
+fn try_tokenize_unknown_char() {
+    assert_eq!(
+        try_tokenize("a ~ \"b"),
+        Err(LexError {
+            offset: 2,
+            kind: LexErrorKind::UnknownChar('~'),
+        })
+    );
+}
+
+#[test]
+fn try_tokenize_unterminated_string() {
+    assert_eq!(
+        try_tokenize("x = \"abc"),
+        Err(LexError {
+            offset: 4,
+            kind: LexErrorKind::UnterminatedString,
+        })
+    );
+    let input = "x b\"abc";
+    assert_eq!(
+        try_tokenize(input),
+        Err(LexError {
+            offset: 2,
+            kind: LexErrorKind::UnterminatedString,
+        })
+    );
+    assert_eq!(lex_stats(input).n_unterminated_strings, 1);
+}
+
+#[test]
+fn try_tokenize_unterminated_block_comment() {
+    let err = try_tokenize("/* a /* b").unwrap_err();
+    assert_eq!(
+        err,
+        LexError {
+            offset: 0,
+            kind: LexErrorKind::UnterminatedBlockComment {
+                open: Span { lo: 0, hi: 2 }
+            },
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "unterminated block comment started at offset 0"
+    );
+
+    let err = try_tokenize("x /* a /* b */ c").unwrap_err();
+    assert_eq!(
+        err.kind,
+        LexErrorKind::UnterminatedBlockComment {
+            open: Span { lo: 2, hi: 4 }
+        }
+    );
+    assert!(try_tokenize("/* a /* b */ c */").is_ok());
+}
+
+#[test]
+fn tokenizer_is_nameable() {
+    struct Holder<'a> {
+        tokens: std::iter::Peekable<Tokenizer<'a>>,
+    }
+
+    let input = "fn main() {}";
+    let mut holder = Holder {
+        tokens: tokenize(input).peekable(),
+    };
+    assert_eq!(
+        holder.tokens.peek().map(|t| t.kind),
+        Some(Keyword(Keyword::Fn))
+    );
+    let stored: Tokenizer<'_> = tokenize(input);
+    assert_eq!(
+        stored.collect::<Vec<_>>(),
+        holder.tokens.collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn strip_trivia_fn_main() {
+    let input = "fn main() {\n    // greet\n    println!(\"hello world\"); /* done */\n}\n";
+    let significant: Vec<TokenKind> = strip_trivia(tokenize(input)).map(|t| t.kind).collect();
+    assert_eq!(
+        significant,
+        [
+            Keyword(Keyword::Fn),
+            Identifier,
+            OpenParen,
+            CloseParen,
+            OpenBrace,
+            Identifier,
+            Exclam,
+            OpenParen,
+            str_lit(true, false),
+            CloseParen,
+            Semi,
+            CloseBrace,
+        ]
+    );
+}
+
+#[test]
+fn shebang() {
+    assert_eq!(
+        lex("#!/usr/bin/env rc\nfn"),
+        [(Shebang, 17), (Whitespace, 1), (Keyword(Keyword::Fn), 2)]
+    );
+    assert_eq!(lex("#!"), [(Shebang, 2)]);
+}
+
+#[test]
+fn inner_attribute_is_not_shebang() {
+    assert_eq!(kinds("#![attr]")[..2], [Pound, Exclam]);
+}
+
+#[test]
+fn shebang_only_at_start() {
+    assert_eq!(kinds(" #!x"), [Whitespace, Pound, Exclam, Identifier]);
+    assert_eq!(kinds("a\n#!x")[2..4], [Pound, Exclam]);
+}
+
+#[test]
+fn byte_str() {
+    assert_eq!(
+        lex(r#"b"ok""#),
+        [(
+            Literal {
+                kind: ByteStr { terminated: true }
+            },
+            5
+        )]
+    );
+    assert_eq!(
+        lex(r#"b"a\"b"#),
+        [(
+            Literal {
+                kind: ByteStr { terminated: false }
+            },
+            6
+        )]
+    );
+}
+
+#[test]
+fn byte() {
+    assert_eq!(
+        lex("b'x'"),
+        [(
+            Literal {
+                kind: Byte { terminated: true }
+            },
+            4
+        )]
+    );
+    assert_eq!(
+        lex(r"b'\''"),
+        [(
+            Literal {
+                kind: Byte { terminated: true }
+            },
+            5
+        )]
+    );
+}
+
+#[test]
+fn b_identifier() {
+    assert_eq!(lex("bat"), [(Identifier, 3)]);
+    assert_eq!(lex("b"), [(Identifier, 1)]);
+}
+
+#[test]
+fn underscore_separators() {
+    assert_eq!(lex("1_000"), [(dec(5), 5)]);
+    assert_eq!(lex("1_"), [(dec(2), 2)]);
+    assert_eq!(lex("0xDE_AD"), [(int(Base::Hexadecimal, false, 7), 7)]);
+    assert_eq!(lex("0x_"), [(int(Base::Hexadecimal, true, 3), 3)]);
+    assert_eq!(lex("1_000.5e1_0"), [(float(11), 11)]);
+    assert_eq!(lex("_1"), [(Identifier, 2)]);
+}
+
+#[test]
+fn numeric_suffixes() {
+    assert_eq!(lex("42u8"), [(dec(2), 4)]);
+    assert_eq!(lex("0xFFi32"), [(int(Base::Hexadecimal, false, 4), 7)]);
+    assert_eq!(lex("3.0f64"), [(float(3), 6)]);
+    assert_eq!(lex("1e10f32"), [(float(4), 7)]);
+    assert_eq!(lex("1.method()")[0], (dec(1), 1));
+}
+
+#[test]
+fn render_unterminated_string() {
+    let input = "fn main() {\n    let s = \"abc;\n}\n";
+    let token = tokenize(input)
+        .find(|t| matches!(t.kind, Literal { kind: Str { .. } }))
+        .unwrap();
+    let quote = Span {
+        lo: token.span.lo,
+        hi: token.span.lo + 1,
+    };
+    let rendered = render_lex_diagnostic(input, &token, quote);
+    assert_eq!(
+        rendered,
+        "error: string literal (unterminated)\n \
+         --> 2:13\n  \
+         |\n\
+         2 |     let s = \"abc;\n  \
+         |             ^\n"
+    );
+}
+
+#[test]
+fn render_underlines_span_on_one_line() {
+    let input = "\tx ~~ y";
+    let token = tokenize(input).find(|t| t.kind == Unknown('~')).unwrap();
+    let rendered = render_lex_diagnostic(input, &token, Span { lo: 3, hi: 5 });
+    assert!(rendered.contains("1 | \tx ~~ y\n"));
+    assert!(rendered.ends_with("  | \t  ^^\n"));
+}
+
+#[test]
+fn tokenize_to_vec_collects() {
+    let tokens = tokenize_to_vec("a b");
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(tokens, tokenize("a b").collect::<Vec<_>>());
+}
+
+#[test]
+fn check_delims_balanced() {
+    let input = "fn f() { g({}) }";
+    assert_eq!(check_delims(input), Ok(tokenize_to_vec(input)));
+}
+
+#[test]
+fn check_delims_unbalanced() {
+    let unmatched = |input| -> Vec<(TokenKind, usize)> {
+        check_delims(input)
+            .unwrap_err()
+            .iter()
+            .map(|t| (t.kind, t.span.lo))
+            .collect()
+    };
+    assert_eq!(unmatched("(a"), [(OpenParen, 0)]);
+    assert_eq!(unmatched("a)"), [(CloseParen, 1)]);
+    assert_eq!(unmatched("{(}"), [(OpenParen, 1)]);
+    assert_eq!(unmatched("(}"), [(OpenParen, 0), (CloseBrace, 1)]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    let tokens = tokenize_to_vec("fn f() { \"s\" 0x1F }");
+    let json = serde_json::to_string(&tokens).unwrap();
+    assert!(json.contains(r#""kind":{"Keyword":"Fn"}"#));
+    assert!(json.contains(r#"{"Int":{"base":"Hexadecimal""#));
+    let back: Vec<Token> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, tokens);
+}
+
+#[test]
+fn tab_width() {
+    let col = |input, tab_width| {
+        let options = TokenizeOptions {
+            tab_width,
+            ..Default::default()
+        };
+        tokenize_with_options(input, options)
+            .last()
+            .unwrap()
+            .location()
+            .col
+    };
+    assert_eq!(col("\tx", 1), 2);
+    assert_eq!(col("\tx", 4), 5);
+    assert_eq!(col("ab\tx", 4), 5);
+    assert_eq!(col("abcd\tx", 4), 9);
+    assert_eq!(col("\tx", 0), 2);
+}
+
+#[test]
+fn comparison_and_shift_operators() {
+    assert_eq!(lex("<"), [(Lt, 1)]);
+    assert_eq!(lex("<="), [(Le, 2)]);
+    assert_eq!(lex("=="), [(EqEq, 2)]);
+    assert_eq!(lex("<<"), [(Shl, 2)]);
+    assert_eq!(lex("!"), [(Exclam, 1)]);
+    assert_eq!(
+        kinds("> >= >> !="),
+        [Gt, Whitespace, Ge, Whitespace, Shr, Whitespace, Ne]
+    );
+    assert_eq!(kinds("<<="), [Shl, Eq]);
+    assert_eq!(kinds("!!="), [Exclam, Ne]);
+}
+
+#[test]
+fn comma_colon_dot_brackets() {
+    assert_eq!(lex(","), [(Comma, 1)]);
+    assert_eq!(lex(":"), [(Colon, 1)]);
+    assert_eq!(lex("."), [(Dot, 1)]);
+    assert_eq!(lex("["), [(OpenBracket, 1)]);
+    assert_eq!(lex("]"), [(CloseBracket, 1)]);
+    assert_eq!(kinds(".5"), [Dot, dec(1)]);
+    assert_eq!(kinds("x.0"), [Identifier, Dot, dec(1)]);
+}
+
+#[test]
+fn logical_and_bitwise_operators() {
+    assert_eq!(lex("&&"), [(AndAnd, 2)]);
+    assert_eq!(lex("|"), [(Or, 1)]);
+    assert_eq!(lex("||"), [(OrOr, 2)]);
+    assert_eq!(lex("^"), [(Caret, 1)]);
+    assert_eq!(lex("&"), [(And, 1)]);
+    assert_eq!(kinds("&&&"), [AndAnd, And]);
+    assert_eq!(kinds("a|b"), [Identifier, Or, Identifier]);
+}
+
+#[test]
+fn is_trivia() {
+    assert!(Whitespace.is_trivia());
+    assert!(LINE_COMMENT.is_trivia());
+    assert!(block(true).is_trivia());
+    assert!(block(false).is_trivia());
+    assert!(!Identifier.is_trivia());
+    assert!(!Semi.is_trivia());
+    assert!(!Shebang.is_trivia());
+    assert!(!str_lit(true, false).is_trivia());
+    assert!(!Eof.is_trivia());
+}
+
+#[test]
+fn doc_comments() {
+    let line = |doc| LineComment {
+        kind: CommentKind { doc },
+    };
+    assert_eq!(lex("/// doc"), [(line(Some(DocStyle::Outer)), 7)]);
+    assert_eq!(lex("//! inner"), [(line(Some(DocStyle::Inner)), 9)]);
+    assert_eq!(lex("//// normal"), [(line(None), 11)]);
+    assert_eq!(lex("// plain"), [(line(None), 8)]);
+    assert_eq!(lex("///"), [(line(Some(DocStyle::Outer)), 3)]);
+
+    let doc_block = |doc| BlockComment {
+        kind: CommentKind { doc },
+        terminated: true,
+    };
+    assert_eq!(kinds("/** doc */"), [doc_block(Some(DocStyle::Outer))]);
+    assert_eq!(kinds("/*! inner */"), [doc_block(Some(DocStyle::Inner))]);
+    assert_eq!(kinds("/*** normal */"), [doc_block(None)]);
+    assert_eq!(kinds("/**/"), [doc_block(None)]);
+    assert_eq!(
+        line(Some(DocStyle::Outer)).to_string(),
+        "outer doc line comment"
+    );
+}
+
+/// A reader that hands out its input a few bytes at a time.
+struct Trickle<'a> {
+    input: &'a [u8],
+    step: usize,
+}
+
+impl std::io::Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.step.min(self.input.len()).min(buf.len());
+        buf[..n].copy_from_slice(&self.input[..n]);
+        self.input = &self.input[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn tokenize_reader_matches_tokenize() {
+    let input =
+        "#!/bin/rc\nfn main() {\n    let s = \"caf\u{e9}, a long string\";\n    1.5e3 'a ''\n}";
+    let expected = tokenize_to_vec(input);
+    let from_slice: Vec<Token> = tokenize_reader(input.as_bytes())
+        .collect::<std::io::Result<_>>()
+        .unwrap();
+    assert_eq!(from_slice, expected);
+    for step in 1..8 {
+        let reader = Trickle {
+            input: input.as_bytes(),
+            step,
+        };
+        let tokens: Vec<Token> = tokenize_reader(reader)
+            .collect::<std::io::Result<_>>()
+            .unwrap();
+        assert_eq!(tokens, expected, "step {step}");
+    }
+}
+
+#[test]
+fn tokenize_reader_long_tokens() {
+    let body = "x".repeat(2 << 20);
+    for input in [
+        format!("a \"{body}\" b"),
+        format!("a /* {body}"),
+        format!("{body} b"),
+    ] {
+        let tokens: Vec<Token> = tokenize_reader(input.as_bytes())
+            .collect::<std::io::Result<_>>()
+            .unwrap();
+        assert_eq!(tokens, tokenize_to_vec(&input));
+    }
+}
+
+#[test]
+fn tokenize_reader_shebang_only_at_start() {
+    let input = "a\n#!b";
+    let reader = Trickle {
+        input: input.as_bytes(),
+        step: 2,
+    };
+    let tokens: Vec<Token> = tokenize_reader(reader).map(Result::unwrap).collect();
+    assert_eq!(tokens, tokenize_to_vec(input));
+    assert!(tokens.iter().all(|t| t.kind != Shebang));
+}
+
+#[test]
+fn tokenize_reader_invalid_utf8() {
+    let mut tokens = tokenize_reader(&b"a \xff"[..]);
+    let err = tokens.next().unwrap().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(tokens.next().is_none());
+
+    let truncated = tokenize_reader(&b"a \xc3"[..]).last().unwrap();
+    assert!(truncated.is_err());
+}
+
+#[test]
+fn multi_byte_lengths() {
+    let input = "café \"é\" 'é'";
+    assert_eq!(
+        lex(input),
+        [
+            (Identifier, 5),
+            (Whitespace, 1),
+            (str_lit(true, false), 4),
+            (Whitespace, 1),
+            (
+                Literal {
+                    kind: Char { terminated: true }
+                },
+                4
+            ),
+        ]
+    );
+    let tokens = tokenize_to_vec(input);
+    assert_eq!(tokens[2].span, Span { lo: 6, hi: 10 });
+    assert_eq!(tokens[2].text(input), "\"é\"");
+}
+
+#[test]
+fn crlf() {
+    let input = "a\r\nb";
+    assert_eq!(
+        lex(input),
+        [(Identifier, 1), (Whitespace, 2), (Identifier, 1)]
+    );
+    let b = tokenize(input).last().unwrap();
+    assert_eq!(b.location(), Location { line: 2, col: 1 });
+}
+
+#[test]
+fn line_comment_before_crlf() {
+    assert_eq!(
+        lex("// a\r\nb"),
+        [(LINE_COMMENT, 4), (Whitespace, 2), (Identifier, 1)]
+    );
+    assert_eq!(lex("// a\rb"), [(LINE_COMMENT, 6)]);
+}
+
+#[test]
+fn eat_token_at_end_of_input() {
+    assert_eq!(Cursor::new("").eat_token(&Keywords::default()), Eof);
+}
+
+#[test]
+fn adversarial_inputs_terminate() {
+    let long_string = format!("\"{}", "\\".repeat(10_000));
+    let deep = "{(".repeat(50_000);
+    let inputs = [
+        "",
+        "\0",
+        "\0\0\0",
+        "\u{1}\u{7f}\u{8}\u{1b}",
+        "'",
+        "''",
+        "''''",
+        "'\\",
+        "\"",
+        "\"\\",
+        "\"\"\"",
+        "{{{{",
+        "}}}}",
+        "r",
+        "r#",
+        "r#\"",
+        "r##\"a\"#",
+        "b",
+        "b'",
+        "b\"\\",
+        "/*",
+        "/*/",
+        "//",
+        "0x",
+        "0b_",
+        "1e",
+        "1e+",
+        "1.",
+        "#!",
+        "#!\n#!",
+        "\r",
+        "e\u{301}",
+        "\u{10ffff}",
+        &long_string,
+        &deep,
+    ];
+    for input in inputs {
+        let tokens = tokenize_to_vec(input);
+        let mut offset = 0;
+        for token in &tokens {
+            assert_eq!(token.span.lo, offset, "gap in {input:?}");
+            assert!(token.len > 0, "empty token in {input:?}");
+            offset = token.span.hi;
+        }
+        assert_eq!(offset, input.len(), "{input:?} not fully consumed");
+    }
+}
+
+#[test]
+fn count_lines() {
+    let count = |input| super::count_lines(&tokenize_to_vec(input), input);
+    assert_eq!(count("fn main() {\n    println!(\"hello world\");\n}\n"), 3);
+    assert_eq!(count("fn main() {}"), 1);
+    assert_eq!(count(""), 0);
+    assert_eq!(count("a\r\n\n// c\n/* d\n */ \"e\nf\""), 6);
+}
+
+#[test]
+fn span_to() {
+    let a = Span { lo: 0, hi: 2 };
+    let b = Span { lo: 2, hi: 5 };
+    let c = Span { lo: 8, hi: 9 };
+    assert_eq!(a.to(b), Span { lo: 0, hi: 5 });
+    assert_eq!(a.to(c), Span { lo: 0, hi: 9 });
+    assert_eq!(b.to(b), b);
+    assert_eq!(Span::dummy(), Span { lo: 0, hi: 0 });
+}
+
+#[test]
+fn question() {
+    assert_eq!(lex("foo?"), [(Identifier, 3), (Question, 1)]);
+    assert_eq!(kinds("??"), [Question, Question]);
+}
+
+#[test]
+fn pound() {
+    assert_eq!(lex("#"), [(Pound, 1)]);
+    assert_eq!(
+        kinds("#[test]"),
+        [Pound, OpenBracket, Identifier, CloseBracket]
+    );
+    assert_eq!(
+        kinds("#![allow]"),
+        [Pound, Exclam, OpenBracket, Identifier, CloseBracket]
+    );
+}
+
+#[test]
+fn shebang_vs_pound() {
+    assert_eq!(kinds("#!/bin/rc\n#!"), [Shebang, Whitespace, Pound, Exclam]);
+    assert_eq!(
+        kinds("x\n#!/bin/rc")[..4],
+        [Identifier, Whitespace, Pound, Exclam]
+    );
+}
+
+#[test]
+fn at_and_dollar() {
+    assert_eq!(lex("@"), [(At, 1)]);
+    assert_eq!(lex("$"), [(Dollar, 1)]);
+    assert_eq!(kinds("$x"), [Dollar, Identifier]);
+    assert_eq!(
+        kinds("x @ 1"),
+        [Identifier, Whitespace, At, Whitespace, dec(1)]
+    );
+}
+
+#[test]
+fn coalesce_unknown() {
+    let options = TokenizeOptions {
+        coalesce_unknown: true,
+        ..Default::default()
+    };
+    let coalesced: Vec<_> = tokenize_with_options("~~~", options).collect();
+    assert_eq!(coalesced.len(), 1);
+    assert_eq!(coalesced[0].kind, Unknown('~'));
+    assert_eq!(coalesced[0].span, Span { lo: 0, hi: 3 });
+    assert_eq!(kinds("~~~"), [Unknown('~'); 3]);
+
+    let runs: Vec<_> = tokenize_with_options("~\u{a7}~+~", options)
+        .map(|t| (t.kind, t.span.hi - t.span.lo))
+        .collect();
+    assert_eq!(runs, [(Unknown('~'), 4), (Plus, 1), (Unknown('~'), 1)]);
+}
+
+#[test]
+fn delimiters() {
+    for (open, close) in [
+        (OpenParen, CloseParen),
+        (OpenBrace, CloseBrace),
+        (OpenBracket, CloseBracket),
+    ] {
+        assert!(open.is_open_delim() && !open.is_close_delim());
+        assert!(close.is_close_delim() && !close.is_open_delim());
+        assert_eq!(open.matching_close(), Some(close));
+        assert_eq!(close.matching_close(), None);
+    }
+    for kind in [Lt, Gt, Semi, Identifier, Unknown('(')] {
+        assert!(!kind.is_open_delim() && !kind.is_close_delim());
+        assert_eq!(kind.matching_close(), None);
+    }
+}
+
+#[test]
+fn relex_matches_full_tokenize() {
+    let edits = [
+        ("fn main() { x + 1 }", 15..16, "y"),
+        ("fn main() { x + 1 }", 12..13, "longer_name"),
+        ("fn main() {\n    x\n}\ny", 16..17, "\"a\n\nb"),
+        ("let s = \"abc\";\nlet t = 1;", 12..13, ""),
+        ("a  \n  b", 3..3, " "),
+        ("1 . 2", 1..4, ""),
+        ("// note\nx\r\ny", 8..9, "\t/* z */"),
+        ("#!/bin/rc\nfn f() {}", 0..0, " "),
+        ("", 0..0, "fn"),
+        ("x", 0..1, ""),
+    ];
+    for (old, edit, replacement) in edits {
+        let mut src = old.to_string();
+        src.replace_range(edit.clone(), replacement);
+        let old_tokens = tokenize_to_vec(old);
+        assert_eq!(
+            relex(&old_tokens, &src, edit),
+            tokenize_to_vec(&src),
+            "{src:?}"
+        );
+    }
+}
+
+#[test]
+fn tokenize_into_reuses_buffer() {
+    let mut out = Vec::with_capacity(64);
+    let capacity = out.capacity();
+    for input in ["fn main() {}", "x + 1", ""] {
+        tokenize_into(input, &mut out);
+        assert_eq!(out, tokenize(input).collect::<Vec<_>>());
+        assert_eq!(out.capacity(), capacity);
+    }
+}
+
+#[test]
+fn token_kinds_in_hash_set() {
+    let set: std::collections::HashSet<TokenKind> = kinds("fn x() { 1 + 1 }").into_iter().collect();
+    assert!(set.contains(&Keyword(Keyword::Fn)));
+    assert!(set.contains(&Identifier));
+    assert!(set.contains(&dec(1)));
+    assert!(set.contains(&Plus));
+    assert!(!set.contains(&Minus));
+    assert!(!set.contains(&dec(2)));
+    assert_eq!(set.iter().filter(|kind| **kind == Whitespace).count(), 1);
+}
+
+#[test]
+fn histogram() {
+    let counts = token_histogram("fn f(a, b) { a + b + 1 }");
+    assert_eq!(counts["identifier"], 5);
+    assert_eq!(counts["whitespace"], 9);
+    assert_eq!(counts["keyword"], 1);
+    assert_eq!(counts["+"], 2);
+    assert_eq!(counts["literal"], 1);
+    assert!(!counts.contains_key("-"));
+}
+
+#[test]
+fn display_uses_name() {
+    assert_eq!(Keyword(Keyword::Fn).to_string(), "keyword `fn`");
+    assert_eq!(Unknown('~').to_string(), "unknown character `~`");
+    assert_eq!(block(false).to_string(), "block comment (unterminated)");
+    assert_eq!(PathSep.to_string(), PathSep.name());
+}
+
+#[test]
+fn ranges() {
+    assert_eq!(kinds("."), [Dot]);
+    assert_eq!(kinds(".."), [DotDot]);
+    assert_eq!(kinds("..="), [DotDotEq]);
+    assert_eq!(kinds("..."), [DotDot, Dot]);
+    assert_eq!(kinds("1..2"), [dec(1), DotDot, dec(1)]);
+    assert_eq!(kinds("1..=2"), [dec(1), DotDotEq, dec(1)]);
+    assert_eq!(kinds("1.5.."), [float(3), DotDot]);
+}
+
+#[test]
+fn compound_assignment() {
+    assert_eq!(kinds("+="), [PlusEq]);
+    assert_eq!(kinds("-"), [Minus]);
+    assert_eq!(
+        kinds("-= *= %="),
+        [MinusEq, Whitespace, StarEq, Whitespace, PercentEq]
+    );
+    assert_eq!(kinds("/="), [SlashEq]);
+    assert_eq!(kinds("//="), [LINE_COMMENT]);
+    assert_eq!(kinds("/*=*/"), [block(true)]);
+    assert_eq!(kinds("+ ="), [Plus, Whitespace, Eq]);
+    assert_eq!(kinds("->"), [Arrow]);
+}
+
+#[test]
+fn lex_stats_fn_main() {
+    let input = "fn main() {\n    println!(\"hello world\");\n}\n";
+    assert_eq!(
+        lex_stats(input),
+        LexStats {
+            n_tokens: 17,
+            n_lines: 3,
+            n_unterminated_strings: 0,
+            n_unknown: 0,
+        }
+    );
+    let tokens = tokenize_to_vec(input);
+    assert_eq!(lex_stats(input).n_lines, super::count_lines(&tokens, input));
+
+    let stats = lex_stats("~ \"a\nb ~");
+    assert_eq!(stats.n_tokens, 3);
+    assert_eq!(stats.n_lines, 2);
+    assert_eq!(stats.n_unterminated_strings, 1);
+    assert_eq!(stats.n_unknown, 1);
+    assert_eq!(lex_stats(""), LexStats::default());
+}
+
+#[test]
+fn embedded_null() {
+    assert_eq!(
+        lex("a\0b"),
+        [(Identifier, 1), (Unknown('\0'), 1), (Identifier, 1)]
+    );
+    assert_eq!(
+        lex("\"\0\" x"),
+        [(str_lit(true, false), 3), (Whitespace, 1), (Identifier, 1)]
+    );
+    assert_eq!(
+        lex("'\0'"),
+        [(
+            Literal {
+                kind: Char { terminated: true }
+            },
+            3
+        )]
+    );
+    assert_eq!(
+        lex("// \0 x\ny"),
+        [(LINE_COMMENT, 6), (Whitespace, 1), (Identifier, 1)]
+    );
+    assert_eq!(lex("/* \0 */"), [(block(true), 7)]);
+    assert_eq!(lex("\0"), [(Unknown('\0'), 1)]);
+}
+
+#[test]
+fn tokenizer_is_fused_with_size_hint() {
+    let mut tokens = tokenize("ab c");
+    assert_eq!(tokens.size_hint(), (1, Some(4)));
+    tokens.next();
+    assert_eq!(tokens.size_hint(), (1, Some(2)));
+    tokens.next();
+    tokens.next();
+    assert_eq!(tokens.size_hint(), (0, Some(0)));
+    assert_eq!(tokens.next(), None);
+    assert_eq!(tokens.next(), None);
+    assert_eq!(tokenize("").size_hint(), (0, Some(0)));
+}
+
+#[test]
+fn custom_keywords() {
+    const DIALECT: &[(&str, Keyword)] = &[("func", Keyword::Fn), ("let", Keyword::Let)];
+    let options = TokenizeOptions {
+        keywords: Keywords::new(DIALECT),
+        ..Default::default()
+    };
+    let dialect: Vec<_> = tokenize_with_options("func fn let", options)
+        .map(|t| t.kind)
+        .collect();
+    assert_eq!(
+        dialect,
+        [
+            Keyword(Keyword::Fn),
+            Whitespace,
+            Identifier,
+            Whitespace,
+            Keyword(Keyword::Let)
+        ]
+    );
+    assert_eq!(
+        kinds("func fn"),
+        [Identifier, Whitespace, Keyword(Keyword::Fn)]
+    );
+    assert_eq!(Keywords::default().lookup("while"), Some(Keyword::While));
+    assert_eq!(Keywords::new(DIALECT).lookup("while"), None);
+}
@@ -0,0 +1,4 @@
+//! A basic compiler for a Rust-like language, written as a tutorial.
+
+pub mod lexer;
+pub mod parser;
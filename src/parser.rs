@@ -0,0 +1,61 @@
+//! Turns tokens into an abstract syntax tree.
+
+pub mod ast;
+#[allow(clippy::module_inception)]
+mod parser;
+pub mod symbol;
+pub mod token;
+pub mod tokenstream;
+
+pub use parser::Parser;
+
+use token::{Token, TokenKind};
+
+/// The parser expected one thing but found a different token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub expected: Expected,
+    pub found: Token,
+}
+
+/// What the parser was looking for when it failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expected {
+    Token(TokenKind),
+    /// Any identifier.
+    Ident,
+    /// The start of an expression.
+    Expr,
+    /// Anything but another opening delimiter, since groups are already
+    /// nested this many levels deep.
+    MaxDepth(usize),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.expected {
+            Expected::Token(kind) => write!(f, "expected `{kind}`")?,
+            Expected::Ident => f.write_str("expected identifier")?,
+            Expected::Expr => f.write_str("expected expression")?,
+            Expected::MaxDepth(depth) => {
+                write!(f, "expected delimiters nested at most {depth} deep")?
+            }
+        }
+        write!(f, ", found `{}`", self.found)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub type Result<T> = std::result::Result<T, ParseError>;
+
+/// Parses `tokens` as a whole program, failing with every error found.
+pub fn parse_program(tokens: Vec<Token>) -> std::result::Result<ast::Program, Vec<ParseError>> {
+    let mut parser = Parser::new(tokens);
+    let program = parser.parse_program();
+    if parser.errors().is_empty() {
+        Ok(program)
+    } else {
+        Err(parser.errors().to_vec())
+    }
+}
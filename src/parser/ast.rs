@@ -0,0 +1,336 @@
+//! The abstract syntax tree produced by the parser.
+
+use super::symbol::Symbol;
+use super::token::{BinOpToken, Lit, TokenKind};
+use super::tokenstream::TokenStream;
+
+/// A whole source file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub items: Vec<Item>,
+}
+
+/// A top-level declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Fn(Fn),
+}
+
+/// A function declaration, `fn name() { ... }`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fn {
+    pub name: Symbol,
+    pub body: Block,
+}
+
+/// `{ stmts tail }`, where the optional tail expression is the value of the
+/// block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub tail: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    /// An expression without a trailing `;`, such as a nested block that is
+    /// not the last thing in its block.
+    Expr(Expr),
+    /// `expr;`
+    Semi(Expr),
+    /// `let name = init;`
+    Let { name: Symbol, init: Expr },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A literal such as `42` or `"hi"`.
+    Lit(Lit),
+    /// A name such as `foo`.
+    Path(Symbol),
+    /// `{ ... }`
+    Block(Box<Block>),
+    /// `callee(args...)`
+    Call { callee: Box<Expr>, args: Vec<Expr> },
+    /// `name!(args...)`, with the arguments left as unparsed tokens.
+    MacroCall { name: Symbol, args: TokenStream },
+    /// `lhs op rhs`
+    Binary {
+        op: BinOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOp {
+    /// `=`
+    Assign,
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `%`
+    Rem,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+impl Expr {
+    /// Whether the expression ends in a `}`, so that it needs no `;` to be
+    /// used as a statement.
+    pub fn is_block_like(&self) -> bool {
+        matches!(self, Expr::Block(_))
+    }
+}
+
+impl BinOp {
+    /// The operator a token stands for in infix position, if any.
+    pub fn from_token(kind: &TokenKind) -> Option<BinOp> {
+        let op = match kind {
+            TokenKind::Eq => BinOp::Assign,
+            TokenKind::BinOp(BinOpToken::Plus) => BinOp::Add,
+            TokenKind::BinOp(BinOpToken::Minus) => BinOp::Sub,
+            TokenKind::BinOp(BinOpToken::Star) => BinOp::Mul,
+            TokenKind::BinOp(BinOpToken::Slash) => BinOp::Div,
+            TokenKind::BinOp(BinOpToken::Percent) => BinOp::Rem,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// How tightly the operator binds; higher binds tighter. Always at
+    /// least 1.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinOp::Assign => 1,
+            BinOp::Add | BinOp::Sub => 2,
+            BinOp::Mul | BinOp::Div | BinOp::Rem => 3,
+        }
+    }
+
+    pub fn assoc(self) -> Assoc {
+        match self {
+            BinOp::Assign => Assoc::Right,
+            _ => Assoc::Left,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BinOp::Assign => "=",
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+        }
+    }
+}
+
+/// Renders `item` as formatted source: one statement per line, indented by
+/// four spaces per block.
+pub fn pretty_print(item: &Fn) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        indent: 0,
+    };
+    printer.print_fn(item);
+    printer.out
+}
+
+struct Printer {
+    out: String,
+    /// Current nesting depth in blocks.
+    indent: usize,
+}
+
+impl Printer {
+    fn print_fn(&mut self, item: &Fn) {
+        self.out.push_str(&format!("fn {}() ", item.name));
+        self.print_block(&item.body);
+        self.out.push('\n');
+    }
+
+    /// Prints `block` starting at the current position, leaving the output
+    /// just after its closing `}`.
+    fn print_block(&mut self, block: &Block) {
+        if block.stmts.is_empty() && block.tail.is_none() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push_str("{\n");
+        self.indent += 1;
+        for stmt in &block.stmts {
+            self.print_indent();
+            match stmt {
+                Stmt::Expr(expr) => self.print_expr(expr),
+                Stmt::Semi(expr) => {
+                    self.print_expr(expr);
+                    self.out.push(';');
+                }
+                Stmt::Let { name, init } => {
+                    self.out.push_str(&format!("let {name} = "));
+                    self.print_expr(init);
+                    self.out.push(';');
+                }
+            }
+            self.out.push('\n');
+        }
+        if let Some(tail) = &block.tail {
+            self.print_indent();
+            self.print_expr(tail);
+            self.out.push('\n');
+        }
+        self.indent -= 1;
+        self.print_indent();
+        self.out.push('}');
+    }
+
+    fn print_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Lit(lit) => self.out.push_str(&lit.to_string()),
+            Expr::Path(name) => self.out.push_str(&name.to_string()),
+            Expr::Block(block) => self.print_block(block),
+            Expr::Call { callee, args } => {
+                self.print_expr(callee);
+                self.out.push('(');
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.print_expr(arg);
+                }
+                self.out.push(')');
+            }
+            Expr::MacroCall { name, args } => {
+                self.out.push_str(&format!("{name}!({args})"));
+            }
+            Expr::Binary { op, lhs, rhs } => {
+                let (lhs_min_prec, rhs_min_prec) = match op.assoc() {
+                    Assoc::Left => (op.precedence(), op.precedence() + 1),
+                    Assoc::Right => (op.precedence() + 1, op.precedence()),
+                };
+                self.print_operand(lhs, lhs_min_prec);
+                self.out.push_str(&format!(" {} ", op.as_str()));
+                self.print_operand(rhs, rhs_min_prec);
+            }
+        }
+    }
+
+    /// Prints an operand of a binary expression, in parentheses if its own
+    /// operator binds more loosely than `min_prec`.
+    fn print_operand(&mut self, expr: &Expr, min_prec: u8) {
+        match expr {
+            Expr::Binary { op, .. } if op.precedence() < min_prec => {
+                self.out.push('(');
+                self.print_expr(expr);
+                self.out.push(')');
+            }
+            _ => self.print_expr(expr),
+        }
+    }
+
+    fn print_indent(&mut self) {
+        self.out.push_str(&"    ".repeat(self.indent));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+    use crate::parser::token::Token;
+    use crate::parser::Parser;
+
+    fn parse_fn(src: &str) -> Fn {
+        let tokens = lexer::tokenize(src)
+            .filter_map(|t| Token::from_lexer(&t, src))
+            .collect();
+        Parser::new(tokens).parse_fn().unwrap()
+    }
+
+    fn reformat(src: &str) -> String {
+        pretty_print(&parse_fn(src))
+    }
+
+    #[test]
+    fn pretty_print_let() {
+        let formatted = reformat("fn main() {   let x=y ;}");
+        assert_eq!(formatted, "fn main() {\n    let x = y;\n}\n");
+        assert_eq!(reformat(&formatted), formatted);
+    }
+
+    #[test]
+    fn pretty_print_literals() {
+        let formatted = reformat("fn main() { let x = 1; }");
+        assert_eq!(formatted, "fn main() {\n    let x = 1;\n}\n");
+        assert_eq!(reformat(&formatted), formatted);
+        assert_eq!(
+            reformat("fn f() { 0x1F + 2.5e3 * \"s\" }"),
+            "fn f() {\n    0x1F + 2.5e3 * \"s\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn pretty_print_calls() {
+        assert_eq!(
+            reformat("fn f() { g(); h(1,x+2 ,) }"),
+            "fn f() {\n    g();\n    h(1, x + 2)\n}\n"
+        );
+        assert_eq!(
+            reformat("fn main() { println!(\"hello world\");  }"),
+            "fn main() {\n    println!(\"hello world\");\n}\n"
+        );
+    }
+
+    #[test]
+    fn pretty_print_empty_fn() {
+        assert_eq!(reformat("fn  f(){ }"), "fn f() {}\n");
+    }
+
+    #[test]
+    fn pretty_print_nested_blocks() {
+        assert_eq!(
+            reformat("fn f() { a = b * c; { d; {} } e }"),
+            "fn f() {\n    a = b * c;\n    {\n        d;\n        {}\n    }\n    e\n}\n"
+        );
+    }
+
+    #[test]
+    fn pretty_print_keeps_grouping() {
+        let path = |name| Expr::Path(Symbol::intern(name));
+        let binary = |op, lhs, rhs| Expr::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        };
+        let item = Fn {
+            name: Symbol::intern("f"),
+            body: Block {
+                stmts: vec![],
+                tail: Some(binary(
+                    BinOp::Mul,
+                    binary(BinOp::Add, path("a"), path("b")),
+                    binary(
+                        BinOp::Sub,
+                        path("c"),
+                        binary(BinOp::Sub, path("d"), path("e")),
+                    ),
+                )),
+            },
+        };
+        assert_eq!(
+            pretty_print(&item),
+            "fn f() {\n    (a + b) * (c - (d - e))\n}\n"
+        );
+    }
+}
@@ -0,0 +1,957 @@
+use super::ast;
+use super::symbol::Symbol;
+use super::token::{DelimToken, Keyword, Token, TokenKind};
+use super::tokenstream::{TokenStream, TokenStreamError, DEFAULT_MAX_DEPTH};
+use super::{Expected, ParseError, Result};
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    /// Index of the current token in `tokens`.
+    pos: usize,
+    /// The current token, or [`Token::eof`] once the input is exhausted.
+    pub token: Token,
+    /// Errors that were recovered from, in the order they were found.
+    errors: Vec<ParseError>,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Parser {
+        let token = tokens.first().cloned().unwrap_or_else(Token::eof);
+        Parser {
+            tokens,
+            pos: 0,
+            token,
+            errors: Vec::new(),
+        }
+    }
+
+    /// The errors recovered from so far.
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Records `err` and skips to the next statement boundary, so parsing
+    /// can carry on.
+    pub fn recover(&mut self, err: ParseError) {
+        self.errors.push(err);
+        self.recover_to_stmt_boundary();
+    }
+
+    /// Skips tokens up to and including the next `;`, or up to the `}` that
+    /// closes the current block. Braces in between are skipped as a whole.
+    pub fn recover_to_stmt_boundary(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.token.kind {
+                TokenKind::Eof => return,
+                TokenKind::Semi if depth == 0 => {
+                    self.bump();
+                    return;
+                }
+                TokenKind::OpenDelim(DelimToken::Brace) => depth += 1,
+                TokenKind::CloseDelim(DelimToken::Brace) => {
+                    if depth == 0 {
+                        return;
+                    }
+                    depth -= 1;
+                }
+                _ => {}
+            }
+            self.bump();
+        }
+    }
+
+    /// Advances to the next token.
+    pub fn bump(&mut self) {
+        if self.pos < self.tokens.len() {
+            self.pos += 1;
+        }
+        self.token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .unwrap_or_else(Token::eof);
+    }
+
+    /// Applies `looker` to the token `n` places past the current one, so
+    /// `n == 0` looks at the current token. Past the end of the input,
+    /// `looker` sees an Eof token.
+    pub fn look_nth_tok<R>(&self, n: usize, looker: impl FnOnce(&Token) -> R) -> R {
+        match self.tokens.get(self.pos + n) {
+            Some(token) => looker(token),
+            None => looker(&Token::eof()),
+        }
+    }
+
+    /// Whether the next token to be consumed is `tok`.
+    pub fn check_next_tok(&self, tok: &TokenKind) -> bool {
+        self.token.kind == *tok
+    }
+
+    /// Consumes the next token if it is `tok`, returning whether it did.
+    pub fn eat_if_tok(&mut self, tok: &TokenKind) -> bool {
+        let is_present = self.check_next_tok(tok);
+        if is_present {
+            self.bump();
+        }
+        is_present
+    }
+
+    /// Consumes the next token, which must be `tok`.
+    pub fn eat_tok(&mut self, tok: &TokenKind) -> Result<bool> {
+        if self.eat_if_tok(tok) {
+            Ok(true)
+        } else {
+            Err(ParseError {
+                expected: Expected::Token(tok.clone()),
+                found: self.token.clone(),
+            })
+        }
+    }
+
+    /// Consumes an identifier and returns its name.
+    fn parse_ident(&mut self) -> Result<Symbol> {
+        match &self.token.kind {
+            TokenKind::Ident(name) => {
+                let name = *name;
+                self.bump();
+                Ok(name)
+            }
+            _ => Err(ParseError {
+                expected: Expected::Ident,
+                found: self.token.clone(),
+            }),
+        }
+    }
+
+    /// Like [`Parser::parse_ident`], but returns the identifier's text.
+    pub fn expect_ident(&mut self) -> Result<String> {
+        self.parse_ident().map(|name| name.to_string())
+    }
+
+    /// Parses `open`, then elements separated by `sep` up to `close`. A
+    /// trailing separator is allowed, so `(a, b,)` has two elements.
+    pub fn parse_delimited<T>(
+        &mut self,
+        open: DelimToken,
+        close: DelimToken,
+        sep: TokenKind,
+        mut parse_elem: impl FnMut(&mut Parser) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let close = TokenKind::CloseDelim(close);
+        self.eat_tok(&TokenKind::OpenDelim(open))?;
+        let mut elems = Vec::new();
+        while !self.eat_if_tok(&close) {
+            elems.push(parse_elem(self)?);
+            if !self.eat_if_tok(&sep) {
+                self.eat_tok(&close)?;
+                break;
+            }
+        }
+        Ok(elems)
+    }
+
+    /// Parses items up to the end of the input.
+    ///
+    /// A malformed item is recorded in [`Parser::errors`] and skipped, so the
+    /// result holds every item that did parse.
+    pub fn parse_program(&mut self) -> ast::Program {
+        let mut items = Vec::new();
+        while !self.check_next_tok(&TokenKind::Eof) {
+            match self.parse_fn() {
+                Ok(item) => items.push(ast::Item::Fn(item)),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.recover_to_item_boundary();
+                }
+            }
+        }
+        ast::Program { items }
+    }
+
+    /// Skips tokens up to the next `fn` outside of any braces.
+    fn recover_to_item_boundary(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.token.kind {
+                TokenKind::Eof => return,
+                TokenKind::Keyword(Keyword::Fn) if depth == 0 => return,
+                TokenKind::OpenDelim(DelimToken::Brace) => depth += 1,
+                TokenKind::CloseDelim(DelimToken::Brace) => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.bump();
+        }
+    }
+
+    /// Parses a function declaration, `fn IDENT ( ) BLOCK`.
+    pub fn parse_fn(&mut self) -> Result<ast::Fn> {
+        self.eat_tok(&TokenKind::Keyword(Keyword::Fn))?;
+        let name = self.parse_ident()?;
+        self.eat_tok(&TokenKind::OpenDelim(DelimToken::Paren))?;
+        self.eat_tok(&TokenKind::CloseDelim(DelimToken::Paren))?;
+        let body = self.parse_block()?;
+        Ok(ast::Fn { name, body })
+    }
+
+    /// Parses `{ stmt* expr? }`. An expression directly before the closing
+    /// `}` becomes the block's tail.
+    ///
+    /// A malformed statement is recorded in [`Parser::errors`] and skipped,
+    /// so the block holds every statement that did parse. Running out of
+    /// input before the `}` is an error.
+    pub fn parse_block(&mut self) -> Result<ast::Block> {
+        let close = TokenKind::CloseDelim(DelimToken::Brace);
+        self.eat_tok(&TokenKind::OpenDelim(DelimToken::Brace))?;
+        let mut stmts = Vec::new();
+        let mut tail = None;
+        while !self.eat_if_tok(&close) {
+            if self.check_next_tok(&TokenKind::Eof) {
+                self.eat_tok(&close)?;
+            }
+            match self.parse_stmt() {
+                Ok(ast::Stmt::Expr(expr)) if self.check_next_tok(&close) => tail = Some(expr),
+                Ok(stmt) => stmts.push(stmt),
+                Err(err) if err.found.kind == TokenKind::Eof => return Err(err),
+                Err(err) => self.recover(err),
+            }
+        }
+        Ok(ast::Block { stmts, tail })
+    }
+
+    /// Parses a statement in a block. An expression needs a `;` unless it is
+    /// block-like or directly before the block's closing `}`.
+    fn parse_stmt(&mut self) -> Result<ast::Stmt> {
+        if self.check_next_tok(&TokenKind::Keyword(Keyword::Let)) {
+            return self.parse_let();
+        }
+        let expr = self.parse_expr()?;
+        if self.eat_if_tok(&TokenKind::Semi) {
+            Ok(ast::Stmt::Semi(expr))
+        } else if expr.is_block_like()
+            || self.check_next_tok(&TokenKind::CloseDelim(DelimToken::Brace))
+        {
+            Ok(ast::Stmt::Expr(expr))
+        } else {
+            Err(ParseError {
+                expected: Expected::Token(TokenKind::Semi),
+                found: self.token.clone(),
+            })
+        }
+    }
+
+    /// Parses `let IDENT = EXPR ;`.
+    fn parse_let(&mut self) -> Result<ast::Stmt> {
+        self.eat_tok(&TokenKind::Keyword(Keyword::Let))?;
+        let name = self.parse_ident()?;
+        self.eat_tok(&TokenKind::Eq)?;
+        let init = self.parse_expr()?;
+        self.eat_tok(&TokenKind::Semi)?;
+        Ok(ast::Stmt::Let { name, init })
+    }
+
+    /// Parses an expression, including binary operators.
+    pub fn parse_expr(&mut self) -> Result<ast::Expr> {
+        self.parse_expr_with_precedence(0)
+    }
+
+    /// Parses an expression whose binary operators bind at least as tightly
+    /// as `min_prec`, using precedence climbing.
+    fn parse_expr_with_precedence(&mut self, min_prec: u8) -> Result<ast::Expr> {
+        let mut lhs = self.parse_primary_expr()?;
+        while let Some(op) = ast::BinOp::from_token(&self.token.kind) {
+            let prec = op.precedence();
+            if prec < min_prec {
+                break;
+            }
+            self.bump();
+            let rhs_min_prec = match op.assoc() {
+                ast::Assoc::Left => prec + 1,
+                ast::Assoc::Right => prec,
+            };
+            let rhs = self.parse_expr_with_precedence(rhs_min_prec)?;
+            lhs = ast::Expr::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    /// Parses an expression without binary operators: a literal, a path or
+    /// a block.
+    fn parse_primary_expr(&mut self) -> Result<ast::Expr> {
+        let expr = match &self.token.kind {
+            TokenKind::Literal(lit) => ast::Expr::Lit(lit.clone()),
+            TokenKind::Ident(name) => {
+                let name = *name;
+                self.bump();
+                if self.check_next_tok(&TokenKind::Not) {
+                    return self.parse_macro_call(name);
+                }
+                let path = ast::Expr::Path(name);
+                if self.check_next_tok(&TokenKind::OpenDelim(DelimToken::Paren)) {
+                    return self.parse_call_expr(path);
+                }
+                return Ok(path);
+            }
+            TokenKind::OpenDelim(DelimToken::Brace) => {
+                return Ok(ast::Expr::Block(Box::new(self.parse_block()?)));
+            }
+            _ => {
+                return Err(ParseError {
+                    expected: Expected::Expr,
+                    found: self.token.clone(),
+                })
+            }
+        };
+        self.bump();
+        Ok(expr)
+    }
+
+    /// Parses the `!(...)` of a call to the macro `name`, keeping the tokens
+    /// inside the parentheses as they are.
+    pub fn parse_macro_call(&mut self, name: Symbol) -> Result<ast::Expr> {
+        self.eat_tok(&TokenKind::Not)?;
+        self.eat_tok(&TokenKind::OpenDelim(DelimToken::Paren))?;
+        // Only finds the closing `)`; `TokenStream::from_tokens` checks that
+        // the delimiters in between match up.
+        let mut depth = 0usize;
+        let mut tokens = Vec::new();
+        loop {
+            match &self.token.kind {
+                TokenKind::OpenDelim(_) => depth += 1,
+                TokenKind::CloseDelim(_) if depth > 0 => depth -= 1,
+                TokenKind::CloseDelim(_) | TokenKind::Eof => break,
+                _ => {}
+            }
+            tokens.push(self.token.clone());
+            self.bump();
+        }
+        // The last token `from_tokens` took is the one it failed on, unless
+        // it failed because the tokens ran out.
+        let mut last = None;
+        let args = TokenStream::from_tokens(tokens.into_iter().inspect(|token| {
+            last = Some(token.clone());
+        }))
+        .map_err(|err| {
+            let found = match (&err, last) {
+                (TokenStreamError::Unclosed(_), _) | (_, None) => self.token.clone(),
+                (_, Some(last)) => last,
+            };
+            let expected = match err {
+                TokenStreamError::Unclosed(open)
+                | TokenStreamError::MismatchedClose { open, .. } => {
+                    Expected::Token(TokenKind::CloseDelim(open))
+                }
+                TokenStreamError::UnmatchedClose(_) => {
+                    Expected::Token(TokenKind::CloseDelim(DelimToken::Paren))
+                }
+                TokenStreamError::TooDeep => Expected::MaxDepth(DEFAULT_MAX_DEPTH),
+            };
+            ParseError { expected, found }
+        })?;
+        self.eat_tok(&TokenKind::CloseDelim(DelimToken::Paren))?;
+        Ok(ast::Expr::MacroCall { name, args })
+    }
+
+    /// Parses the parenthesized arguments of a call to `callee`.
+    pub fn parse_call_expr(&mut self, callee: ast::Expr) -> Result<ast::Expr> {
+        let args = self.parse_delimited(
+            DelimToken::Paren,
+            DelimToken::Paren,
+            TokenKind::Comma,
+            Parser::parse_expr,
+        )?;
+        Ok(ast::Expr::Call {
+            callee: Box::new(callee),
+            args,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{self, Span};
+    use crate::parser::token::{Lit, LitKind};
+
+    /// Lexes `src` and sets up a parser over the result.
+    fn parser(src: &str) -> Parser {
+        let tokens = lexer::tokenize(src)
+            .filter_map(|t| Token::from_lexer(&t, src))
+            .collect();
+        Parser::new(tokens)
+    }
+
+    fn tok(kind: TokenKind) -> Token {
+        Token::new(kind, Span::default())
+    }
+
+    fn ident() -> TokenKind {
+        TokenKind::Ident(Symbol::intern("x"))
+    }
+
+    #[test]
+    fn bump_walks_tokens() {
+        let mut parser = Parser::new(vec![
+            tok(ident()),
+            tok(TokenKind::OpenDelim(DelimToken::Paren)),
+            tok(TokenKind::Semi),
+        ]);
+        assert!(matches!(parser.token.kind, TokenKind::Ident(_)));
+        parser.bump();
+        assert!(matches!(
+            parser.token.kind,
+            TokenKind::OpenDelim(DelimToken::Paren)
+        ));
+        parser.bump();
+        assert!(matches!(parser.token.kind, TokenKind::Semi));
+        parser.bump();
+        assert!(matches!(parser.token.kind, TokenKind::Eof));
+        parser.bump();
+        assert!(matches!(parser.token.kind, TokenKind::Eof));
+    }
+
+    #[test]
+    fn eat_if_tok_matching() {
+        let mut parser = Parser::new(vec![tok(TokenKind::Semi), tok(ident())]);
+        assert!(parser.check_next_tok(&TokenKind::Semi));
+        assert!(parser.eat_if_tok(&TokenKind::Semi));
+        assert_eq!(parser.token.kind, ident());
+    }
+
+    #[test]
+    fn eat_if_tok_not_matching() {
+        let mut parser = Parser::new(vec![tok(ident()), tok(TokenKind::Semi)]);
+        assert!(!parser.check_next_tok(&TokenKind::Semi));
+        assert!(!parser.eat_if_tok(&TokenKind::Semi));
+        assert_eq!(parser.token.kind, ident());
+        assert_eq!(parser.pos, 0);
+    }
+
+    #[test]
+    fn eat_tok_matching() {
+        let mut parser = Parser::new(vec![tok(TokenKind::Semi)]);
+        assert_eq!(parser.eat_tok(&TokenKind::Semi), Ok(true));
+        assert_eq!(parser.token.kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn eat_tok_reports_expected_and_found() {
+        let mut parser = Parser::new(vec![tok(ident())]);
+        let err = parser.eat_tok(&TokenKind::Semi).unwrap_err();
+        assert_eq!(err.expected, Expected::Token(TokenKind::Semi));
+        assert_eq!(err.found.kind, ident());
+        assert_eq!(parser.token.kind, ident());
+    }
+
+    #[test]
+    fn look_nth_tok_does_not_consume() {
+        let mut parser = Parser::new(vec![tok(ident()), tok(TokenKind::Semi)]);
+        assert_eq!(parser.look_nth_tok(0, |t| t.kind.clone()), ident());
+        assert_eq!(parser.look_nth_tok(1, |t| t.kind.clone()), TokenKind::Semi);
+        assert_eq!(parser.look_nth_tok(2, |t| t.kind.clone()), TokenKind::Eof);
+        assert_eq!(parser.token.kind, ident());
+        parser.bump();
+        assert_eq!(parser.look_nth_tok(1, |t| t.kind.clone()), TokenKind::Eof);
+    }
+
+    #[test]
+    fn empty_input_starts_at_eof() {
+        let parser = Parser::new(Vec::new());
+        assert!(matches!(parser.token.kind, TokenKind::Eof));
+    }
+
+    #[test]
+    fn parse_fn() {
+        let mut parser = parser("fn main() {}");
+        assert_eq!(
+            parser.parse_fn(),
+            Ok(ast::Fn {
+                name: Symbol::intern("main"),
+                body: ast::Block {
+                    stmts: vec![],
+                    tail: None,
+                },
+            })
+        );
+        assert_eq!(parser.token.kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn parse_fn_missing_name() {
+        let err = parser("fn () {}").parse_fn().unwrap_err();
+        assert_eq!(err.expected, Expected::Ident);
+        assert_eq!(err.found.kind, TokenKind::OpenDelim(DelimToken::Paren));
+        assert_eq!(err.to_string(), "expected identifier, found `(`");
+    }
+
+    #[test]
+    fn parse_fn_missing_body() {
+        let err = parser("fn main();").parse_fn().unwrap_err();
+        assert_eq!(
+            err.expected,
+            Expected::Token(TokenKind::OpenDelim(DelimToken::Brace))
+        );
+        assert_eq!(err.found.kind, TokenKind::Semi);
+    }
+
+    #[test]
+    fn expect_ident() {
+        let mut parser = parser("foo;");
+        assert_eq!(parser.expect_ident(), Ok("foo".to_string()));
+        assert_eq!(parser.token.kind, TokenKind::Semi);
+        let err = parser.expect_ident().unwrap_err();
+        assert_eq!(err.expected, Expected::Ident);
+        assert_eq!(err.found.kind, TokenKind::Semi);
+        assert_eq!(parser.token.kind, TokenKind::Semi);
+    }
+
+    fn parse_parens(parser: &mut Parser) -> Result<Vec<Symbol>> {
+        parser.parse_delimited(
+            DelimToken::Paren,
+            DelimToken::Paren,
+            TokenKind::Comma,
+            Parser::parse_ident,
+        )
+    }
+
+    #[test]
+    fn parse_delimited() {
+        let mut parser = parser("(a, b, c);");
+        assert_eq!(
+            parse_parens(&mut parser),
+            Ok(vec![
+                Symbol::intern("a"),
+                Symbol::intern("b"),
+                Symbol::intern("c")
+            ])
+        );
+        assert_eq!(parser.token.kind, TokenKind::Semi);
+    }
+
+    #[test]
+    fn parse_delimited_empty_and_trailing_sep() {
+        assert_eq!(parse_parens(&mut parser("()")), Ok(vec![]));
+        assert_eq!(
+            parse_parens(&mut parser("(a,)")),
+            Ok(vec![Symbol::intern("a")])
+        );
+    }
+
+    #[test]
+    fn parse_delimited_unterminated() {
+        let err = parse_parens(&mut parser("(a")).unwrap_err();
+        assert_eq!(
+            err.expected,
+            Expected::Token(TokenKind::CloseDelim(DelimToken::Paren))
+        );
+        assert_eq!(err.found.kind, TokenKind::Eof);
+
+        let err = parse_parens(&mut parser("(a,")).unwrap_err();
+        assert_eq!(err.expected, Expected::Ident);
+    }
+
+    #[test]
+    fn recover_to_stmt_boundary() {
+        let recovered = |src| {
+            let mut parser = parser(src);
+            parser.recover_to_stmt_boundary();
+            parser.token.kind
+        };
+        assert_eq!(
+            recovered("a { b; } c; d"),
+            TokenKind::Ident(Symbol::intern("d"))
+        );
+        assert_eq!(
+            recovered("a b } c"),
+            TokenKind::CloseDelim(DelimToken::Brace)
+        );
+        assert_eq!(recovered("a b"), TokenKind::Eof);
+    }
+
+    fn fn_names(program: &ast::Program) -> Vec<String> {
+        program
+            .items
+            .iter()
+            .map(|ast::Item::Fn(item)| item.name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn parse_program() {
+        let mut parser = parser("fn a() {} fn b() { 1 }");
+        let program = parser.parse_program();
+        assert_eq!(fn_names(&program), ["a", "b"]);
+        assert!(parser.errors().is_empty());
+    }
+
+    #[test]
+    fn parse_program_recovers() {
+        let mut parser = parser("fn a() {} fn b( { x } fn c() {} ; fn d() {}");
+        let program = parser.parse_program();
+        assert_eq!(fn_names(&program), ["a", "c", "d"]);
+        let errors = parser.errors();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].expected,
+            Expected::Token(TokenKind::CloseDelim(DelimToken::Paren))
+        );
+        assert_eq!(
+            errors[1].expected,
+            Expected::Token(TokenKind::Keyword(Keyword::Fn))
+        );
+        assert_eq!(errors[1].found.kind, TokenKind::Semi);
+    }
+
+    #[test]
+    fn parse_program_keeps_fn_with_bad_stmt() {
+        let mut parser = parser("fn a() { let = 1; b; c } fn d() {}");
+        let program = parser.parse_program();
+        assert_eq!(fn_names(&program), ["a", "d"]);
+        let ast::Item::Fn(a) = &program.items[0];
+        assert_eq!(
+            a.body,
+            ast::Block {
+                stmts: vec![ast::Stmt::Semi(path("b"))],
+                tail: Some(path("c"))
+            }
+        );
+        assert_eq!(parser.errors().len(), 1);
+    }
+
+    #[test]
+    fn parse_program_fn() {
+        let tokens = |src| parser(src).tokens;
+        let program = crate::parser::parse_program(tokens("fn a() {}")).unwrap();
+        assert_eq!(fn_names(&program), ["a"]);
+        let errors = crate::parser::parse_program(tokens("fn a( fn b() {}")).unwrap_err();
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn parse_expr_literals() {
+        assert_eq!(parser("42").parse_expr(), Ok(int("42")));
+        assert_eq!(
+            parser("\"hi\"").parse_expr(),
+            Ok(ast::Expr::Lit(Lit::new(LitKind::Str, "\"hi\"")))
+        );
+    }
+
+    #[test]
+    fn parse_expr_path() {
+        let mut parser = parser("foo;");
+        assert_eq!(
+            parser.parse_expr(),
+            Ok(ast::Expr::Path(Symbol::intern("foo")))
+        );
+        assert_eq!(parser.token.kind, TokenKind::Semi);
+    }
+
+    #[test]
+    fn parse_expr_error() {
+        let err = parser(";").parse_expr().unwrap_err();
+        assert_eq!(err.expected, Expected::Expr);
+        assert_eq!(err.to_string(), "expected expression, found `;`");
+    }
+
+    fn binary(op: ast::BinOp, lhs: ast::Expr, rhs: ast::Expr) -> ast::Expr {
+        ast::Expr::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        }
+    }
+
+    fn path(name: &str) -> ast::Expr {
+        ast::Expr::Path(Symbol::intern(name))
+    }
+
+    fn call(callee: &str, args: Vec<ast::Expr>) -> ast::Expr {
+        ast::Expr::Call {
+            callee: Box::new(path(callee)),
+            args,
+        }
+    }
+
+    #[test]
+    fn parse_call_expr() {
+        assert_eq!(parser("foo()").parse_expr(), Ok(call("foo", vec![])));
+        assert_eq!(
+            parser("foo(1, 2)").parse_expr(),
+            Ok(call("foo", vec![int("1"), int("2")]))
+        );
+        assert_eq!(
+            parser("foo(1,)").parse_expr(),
+            Ok(call("foo", vec![int("1")]))
+        );
+        assert_eq!(
+            parser("f(g(a), b + 1) * 2").parse_expr(),
+            Ok(binary(
+                ast::BinOp::Mul,
+                call(
+                    "f",
+                    vec![
+                        call("g", vec![path("a")]),
+                        binary(ast::BinOp::Add, path("b"), int("1"))
+                    ]
+                ),
+                int("2")
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_call_expr_errors() {
+        let err = parser("foo(1 2)").parse_expr().unwrap_err();
+        assert_eq!(
+            err.expected,
+            Expected::Token(TokenKind::CloseDelim(DelimToken::Paren))
+        );
+        let err = parser("foo(,)").parse_expr().unwrap_err();
+        assert_eq!(err.expected, Expected::Expr);
+    }
+
+    #[test]
+    fn parse_macro_call() {
+        let expr = parser("println!(\"hi\")").parse_expr().unwrap();
+        let ast::Expr::MacroCall { name, args } = expr else {
+            panic!("expected a macro call");
+        };
+        assert_eq!(name.to_string(), "println");
+        assert_eq!(args.len(), 1);
+        assert_eq!(args.to_string(), "\"hi\"");
+
+        let expr = parser("m!(a, (b [c]) {}) + 1").parse_expr().unwrap();
+        let ast::Expr::Binary { lhs, .. } = expr else {
+            panic!("expected a binary expression");
+        };
+        let ast::Expr::MacroCall { args, .. } = *lhs else {
+            panic!("expected a macro call");
+        };
+        assert_eq!(args.len(), 4);
+        assert_eq!(args.to_string(), "a, (b [c]) {}");
+    }
+
+    #[test]
+    fn parse_macro_call_errors() {
+        let err = parser("m!(a]").parse_expr().unwrap_err();
+        assert_eq!(
+            err.expected,
+            Expected::Token(TokenKind::CloseDelim(DelimToken::Paren))
+        );
+        let err = parser("m!((a)").parse_expr().unwrap_err();
+        assert_eq!(err.found.kind, TokenKind::Eof);
+        let err = parser("m!a").parse_expr().unwrap_err();
+        assert_eq!(
+            err.expected,
+            Expected::Token(TokenKind::OpenDelim(DelimToken::Paren))
+        );
+        let deep = format!("m!({}{})", "(".repeat(300), ")".repeat(300));
+        let err = parser(&deep).parse_expr().unwrap_err();
+        assert_eq!(err.expected, Expected::MaxDepth(DEFAULT_MAX_DEPTH));
+        assert_eq!(err.found.kind, TokenKind::OpenDelim(DelimToken::Paren));
+        assert_eq!(
+            err.to_string(),
+            "expected delimiters nested at most 256 deep, found `(`"
+        );
+        let err = parser("m!((a").parse_expr().unwrap_err();
+        assert_eq!(
+            err.expected,
+            Expected::Token(TokenKind::CloseDelim(DelimToken::Paren))
+        );
+        assert_eq!(err.found.kind, TokenKind::Eof);
+        let err = parser("m!([a").parse_expr().unwrap_err();
+        assert_eq!(
+            err.expected,
+            Expected::Token(TokenKind::CloseDelim(DelimToken::Bracket))
+        );
+        assert_eq!(err.found.kind, TokenKind::Eof);
+        let err = parser("m!([a)])").parse_expr().unwrap_err();
+        assert_eq!(
+            err.expected,
+            Expected::Token(TokenKind::CloseDelim(DelimToken::Bracket))
+        );
+        assert_eq!(err.found.kind, TokenKind::CloseDelim(DelimToken::Paren));
+    }
+
+    #[test]
+    fn binary_precedence() {
+        assert_eq!(
+            parser("1 + 2 * 3").parse_expr(),
+            Ok(binary(
+                ast::BinOp::Add,
+                int("1"),
+                binary(ast::BinOp::Mul, int("2"), int("3"))
+            ))
+        );
+        assert_eq!(
+            parser("1 * 2 + 3").parse_expr(),
+            Ok(binary(
+                ast::BinOp::Add,
+                binary(ast::BinOp::Mul, int("1"), int("2")),
+                int("3")
+            ))
+        );
+    }
+
+    #[test]
+    fn arithmetic_is_left_associative() {
+        assert_eq!(
+            parser("a - b - c").parse_expr(),
+            Ok(binary(
+                ast::BinOp::Sub,
+                binary(ast::BinOp::Sub, path("a"), path("b")),
+                path("c")
+            ))
+        );
+    }
+
+    #[test]
+    fn assignment_is_right_associative() {
+        assert_eq!(
+            parser("a = b = c + d").parse_expr(),
+            Ok(binary(
+                ast::BinOp::Assign,
+                path("a"),
+                binary(
+                    ast::BinOp::Assign,
+                    path("b"),
+                    binary(ast::BinOp::Add, path("c"), path("d"))
+                )
+            ))
+        );
+    }
+
+    fn int(text: &str) -> ast::Expr {
+        ast::Expr::Lit(Lit::new(LitKind::Int, text))
+    }
+
+    #[test]
+    fn parse_block() {
+        assert_eq!(
+            parser("{ }").parse_block(),
+            Ok(ast::Block {
+                stmts: vec![],
+                tail: None
+            })
+        );
+        assert_eq!(
+            parser("{ 1; }").parse_block(),
+            Ok(ast::Block {
+                stmts: vec![ast::Stmt::Semi(int("1"))],
+                tail: None
+            })
+        );
+        assert_eq!(
+            parser("{ 1 }").parse_block(),
+            Ok(ast::Block {
+                stmts: vec![],
+                tail: Some(int("1"))
+            })
+        );
+    }
+
+    #[test]
+    fn parse_block_nested() {
+        let inner = ast::Expr::Block(Box::new(ast::Block {
+            stmts: vec![],
+            tail: Some(path("a")),
+        }));
+        assert_eq!(
+            parser("{ { a } b + 1 }").parse_block(),
+            Ok(ast::Block {
+                stmts: vec![ast::Stmt::Expr(inner)],
+                tail: Some(binary(ast::BinOp::Add, path("b"), int("1")))
+            })
+        );
+    }
+
+    #[test]
+    fn parse_let() {
+        assert_eq!(
+            parser("{ let x = 1; x }").parse_block(),
+            Ok(ast::Block {
+                stmts: vec![ast::Stmt::Let {
+                    name: Symbol::intern("x"),
+                    init: int("1"),
+                }],
+                tail: Some(path("x"))
+            })
+        );
+    }
+
+    /// Parses `src` as a block, which must close, and returns the errors
+    /// recovered from along the way.
+    fn block_errors(src: &str) -> Vec<ParseError> {
+        let mut parser = parser(src);
+        parser.parse_block().unwrap();
+        parser.errors().to_vec()
+    }
+
+    #[test]
+    fn parse_let_errors() {
+        let errors = block_errors("{ let = 1; }");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].expected, Expected::Ident);
+        assert_eq!(errors[0].found.kind, TokenKind::Eq);
+
+        let errors = block_errors("{ let x 1; }");
+        assert_eq!(errors[0].expected, Expected::Token(TokenKind::Eq));
+
+        let errors = block_errors("{ let x = 1 }");
+        assert_eq!(errors[0].expected, Expected::Token(TokenKind::Semi));
+    }
+
+    #[test]
+    fn parse_block_missing_semi() {
+        let errors = block_errors("{ 1 2 }");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].expected, Expected::Token(TokenKind::Semi));
+        assert_eq!(
+            errors[0].found.kind,
+            TokenKind::Literal(Lit::new(LitKind::Int, "2"))
+        );
+
+        let err = parser("{ 1;").parse_block().unwrap_err();
+        assert_eq!(
+            err.expected,
+            Expected::Token(TokenKind::CloseDelim(DelimToken::Brace))
+        );
+        assert_eq!(err.found.kind, TokenKind::Eof);
+
+        let mut nested = parser("{ { 1 + ");
+        let err = nested.parse_block().unwrap_err();
+        assert_eq!(err.expected, Expected::Expr);
+        assert!(nested.errors().is_empty());
+    }
+
+    #[test]
+    fn parse_block_recovers() {
+        let mut parser = parser("{ let = 1; a; b c; d + }");
+        assert_eq!(
+            parser.parse_block(),
+            Ok(ast::Block {
+                stmts: vec![ast::Stmt::Semi(path("a"))],
+                tail: None
+            })
+        );
+        let errors = parser.errors();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].expected, Expected::Ident);
+        assert_eq!(errors[1].expected, Expected::Token(TokenKind::Semi));
+        assert_eq!(errors[2].expected, Expected::Expr);
+        assert_eq!(
+            errors[2].found.kind,
+            TokenKind::CloseDelim(DelimToken::Brace)
+        );
+    }
+
+    #[test]
+    fn binary_missing_rhs() {
+        let err = parser("1 +;").parse_expr().unwrap_err();
+        assert_eq!(err.expected, Expected::Expr);
+    }
+}
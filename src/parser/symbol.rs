@@ -0,0 +1,149 @@
+//! Interned identifiers.
+//!
+//! A [`Symbol`] stands for a string stored once in a [`SymbolInterner`], so
+//! repeated identifiers share their text and compare as cheaply as integers.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+
+/// An interned string, see [`SymbolInterner`].
+///
+/// [`Symbol::intern`] uses one interner shared by the whole process, so a
+/// symbol created that way resolves the same on every thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+/// Maps strings to [`Symbol`]s and back.
+#[derive(Debug, Default)]
+pub struct SymbolInterner {
+    symbols: HashMap<Arc<str>, Symbol>,
+    /// The text of each symbol, indexed by its number.
+    strings: Vec<Arc<str>>,
+}
+
+impl SymbolInterner {
+    pub fn new() -> SymbolInterner {
+        SymbolInterner::default()
+    }
+
+    /// Returns the symbol for `string`, adding it if it is new.
+    pub fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(string) {
+            return symbol;
+        }
+        let index = u32::try_from(self.strings.len()).expect("too many symbols");
+        let symbol = Symbol(index);
+        let string: Arc<str> = string.into();
+        self.strings.push(Arc::clone(&string));
+        self.symbols.insert(string, symbol);
+        symbol
+    }
+
+    /// The text of `symbol`.
+    ///
+    /// # Panics
+    ///
+    /// If `symbol` came from a different interner with more symbols.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.0 as usize]
+    }
+}
+
+/// Locks the process-wide interner. A panic while it was locked cannot have
+/// left it half updated, so poisoning is ignored.
+fn interner() -> MutexGuard<'static, SymbolInterner> {
+    static INTERNER: OnceLock<Mutex<SymbolInterner>> = OnceLock::new();
+    INTERNER
+        .get_or_init(|| Mutex::new(SymbolInterner::new()))
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Symbol {
+    /// Interns `string` in the process-wide interner.
+    pub fn intern(string: &str) -> Symbol {
+        interner().intern(string)
+    }
+
+    /// Calls `f` with the text of a symbol from [`Symbol::intern`].
+    pub fn with_str<R>(self, f: impl FnOnce(&str) -> R) -> R {
+        let string = Arc::clone(&interner().strings[self.0 as usize]);
+        f(&string)
+    }
+
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.with_str(|s| f.write_str(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern_and_resolve() {
+        let mut interner = SymbolInterner::new();
+        let main = interner.intern("main");
+        let x = interner.intern("x");
+        assert_ne!(main, x);
+        assert_eq!(interner.intern("main"), main);
+        assert_eq!(interner.resolve(main), "main");
+        assert_eq!(interner.resolve(x), "x");
+    }
+
+    #[test]
+    fn global_interner() {
+        let a = Symbol::intern("global_a");
+        assert_eq!(Symbol::intern("global_a"), a);
+        assert_ne!(Symbol::intern("global_b"), a);
+        assert_eq!(a.to_string(), "global_a");
+        assert_eq!(a.with_str(str::len), 8);
+    }
+
+    #[test]
+    fn shared_across_threads() {
+        let a = Symbol::intern("thread_a");
+        let (b, a_there) = std::thread::spawn(move || {
+            for i in 0..10 {
+                Symbol::intern(&format!("thread_filler_{i}"));
+            }
+            (Symbol::intern("thread_b"), a.to_string())
+        })
+        .join()
+        .unwrap();
+        assert_eq!(a_there, "thread_a");
+        assert_eq!(b.to_string(), "thread_b");
+        assert_eq!(Symbol::intern("thread_b"), b);
+    }
+
+    #[test]
+    fn with_str_does_not_hold_the_lock() {
+        let a = Symbol::intern("unlocked_a");
+        let b = a.with_str(|s| Symbol::intern(&format!("{s}_b")));
+        assert_eq!(b.to_string(), "unlocked_a_b");
+
+        let panicked = std::panic::catch_unwind(|| a.with_str(|_| panic!("in with_str")));
+        assert!(panicked.is_err());
+        assert_eq!(Symbol::intern("unlocked_a"), a);
+    }
+
+    #[test]
+    fn survives_poisoning() {
+        let a = Symbol::intern("poison_a");
+        let poisoned = std::thread::spawn(|| {
+            let _interner = interner();
+            panic!("while the interner is locked");
+        })
+        .join();
+        assert!(poisoned.is_err());
+        assert_eq!(Symbol::intern("poison_a"), a);
+        assert_eq!(a.to_string(), "poison_a");
+    }
+}
@@ -0,0 +1,585 @@
+//! Tokens as seen by the parser.
+//!
+//! Unlike [`crate::lexer::Token`], these carry no trivia such as whitespace
+//! or comments.
+
+use std::fmt;
+
+use super::symbol::Symbol;
+use crate::lexer::{self, LiteralKind, Span};
+
+pub use crate::lexer::Keyword;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DelimToken {
+    /// `( ... )`
+    Paren,
+    /// `{ ... }`
+    Brace,
+    /// `[ ... ]`
+    Bracket,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinOpToken {
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    And,
+    Or,
+    Caret,
+    Shl,
+    Shr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LitKind {
+    Int,
+    Float,
+    Char,
+    Byte,
+    /// A string or raw string.
+    Str,
+    ByteStr,
+    /// `true` or `false`, which the lexer sees as keywords.
+    Bool,
+    /// A literal the lexer could not make sense of, e.g. an unterminated
+    /// string or a float with no exponent digits.
+    Err,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lit {
+    pub kind: LitKind,
+    /// The literal's source text, e.g. `0x1F` or `"a\n"` with its quotes.
+    pub symbol: Symbol,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// `=`
+    Eq,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `==`
+    EqEq,
+    /// `!=`
+    Ne,
+    /// `>=`
+    Ge,
+    /// `>`
+    Gt,
+    /// `&&`
+    AndAnd,
+    /// `||`
+    OrOr,
+    /// `!`
+    Not,
+    BinOp(BinOpToken),
+    /// A binary operator followed by `=`, such as `+=`.
+    BinOpEq(BinOpToken),
+    /// `:`
+    Colon,
+    /// `::`
+    ModSep,
+    /// `->`
+    RArrow,
+    /// `=>`
+    FatArrow,
+    /// `;`
+    Semi,
+    /// `?`
+    Question,
+    /// `#`
+    Pound,
+    /// `@`
+    At,
+    /// `$`
+    Dollar,
+    /// `,`
+    Comma,
+    /// `.`
+    Dot,
+    /// `..`
+    DotDot,
+    /// `..=`
+    DotDotEq,
+    OpenDelim(DelimToken),
+    CloseDelim(DelimToken),
+    Literal(Lit),
+    Ident(Symbol),
+    Keyword(Keyword),
+    /// A lifetime, including its leading `'`.
+    Lifetime(String),
+    /// A character the lexer did not recognize.
+    Unknown,
+    Eof,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Where the token is in the source, or a default span for tokens that
+    /// were not lexed from any.
+    pub span: Span,
+}
+
+impl DelimToken {
+    pub fn open_str(&self) -> &'static str {
+        match self {
+            DelimToken::Paren => "(",
+            DelimToken::Brace => "{",
+            DelimToken::Bracket => "[",
+        }
+    }
+
+    pub fn close_str(&self) -> &'static str {
+        match self {
+            DelimToken::Paren => ")",
+            DelimToken::Brace => "}",
+            DelimToken::Bracket => "]",
+        }
+    }
+}
+
+impl BinOpToken {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinOpToken::Plus => "+",
+            BinOpToken::Minus => "-",
+            BinOpToken::Star => "*",
+            BinOpToken::Slash => "/",
+            BinOpToken::Percent => "%",
+            BinOpToken::And => "&",
+            BinOpToken::Or => "|",
+            BinOpToken::Caret => "^",
+            BinOpToken::Shl => "<<",
+            BinOpToken::Shr => ">>",
+        }
+    }
+}
+
+impl fmt::Display for Lit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol)
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Eq => f.write_str("="),
+            TokenKind::Lt => f.write_str("<"),
+            TokenKind::Le => f.write_str("<="),
+            TokenKind::EqEq => f.write_str("=="),
+            TokenKind::Ne => f.write_str("!="),
+            TokenKind::Ge => f.write_str(">="),
+            TokenKind::Gt => f.write_str(">"),
+            TokenKind::AndAnd => f.write_str("&&"),
+            TokenKind::OrOr => f.write_str("||"),
+            TokenKind::Not => f.write_str("!"),
+            TokenKind::BinOp(op) => f.write_str(op.as_str()),
+            TokenKind::BinOpEq(op) => write!(f, "{}=", op.as_str()),
+            TokenKind::Colon => f.write_str(":"),
+            TokenKind::ModSep => f.write_str("::"),
+            TokenKind::RArrow => f.write_str("->"),
+            TokenKind::FatArrow => f.write_str("=>"),
+            TokenKind::Semi => f.write_str(";"),
+            TokenKind::Question => f.write_str("?"),
+            TokenKind::Pound => f.write_str("#"),
+            TokenKind::At => f.write_str("@"),
+            TokenKind::Dollar => f.write_str("$"),
+            TokenKind::Comma => f.write_str(","),
+            TokenKind::Dot => f.write_str("."),
+            TokenKind::DotDot => f.write_str(".."),
+            TokenKind::DotDotEq => f.write_str("..="),
+            TokenKind::OpenDelim(delim) => f.write_str(delim.open_str()),
+            TokenKind::CloseDelim(delim) => f.write_str(delim.close_str()),
+            TokenKind::Literal(lit) => write!(f, "{lit}"),
+            TokenKind::Ident(name) => write!(f, "{name}"),
+            TokenKind::Lifetime(name) => f.write_str(name),
+            TokenKind::Keyword(kw) => f.write_str(kw.as_str()),
+            TokenKind::Unknown => f.write_str("<unknown>"),
+            TokenKind::Eof => f.write_str("<eof>"),
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl Token {
+    pub fn new(kind: TokenKind, span: Span) -> Token {
+        Token { kind, span }
+    }
+
+    /// The token the parser sees once it has run out of input.
+    pub fn eof() -> Token {
+        Token::new(TokenKind::Eof, Span::dummy())
+    }
+
+    /// Converts a token produced by the lexer from `src`. Returns `None` for
+    /// trivia, which the parser never sees.
+    pub fn from_lexer(token: &lexer::Token, src: &str) -> Option<Token> {
+        use lexer::TokenKind as L;
+
+        let text = token.text(src);
+        let kind = match token.kind {
+            L::LineComment { .. } | L::BlockComment { .. } | L::Whitespace | L::Shebang => {
+                return None
+            }
+            L::Identifier => TokenKind::Ident(Symbol::intern(text)),
+            L::Keyword(Keyword::True | Keyword::False) => {
+                TokenKind::Literal(Lit::new(LitKind::Bool, text))
+            }
+            L::Keyword(kw) => TokenKind::Keyword(kw),
+            L::Lifetime => TokenKind::Lifetime(text.to_string()),
+            L::Literal { kind } => TokenKind::Literal(Lit::from_lexer(kind, text)),
+            L::Semi => TokenKind::Semi,
+            L::OpenParen => TokenKind::OpenDelim(DelimToken::Paren),
+            L::CloseParen => TokenKind::CloseDelim(DelimToken::Paren),
+            L::OpenBrace => TokenKind::OpenDelim(DelimToken::Brace),
+            L::CloseBrace => TokenKind::CloseDelim(DelimToken::Brace),
+            L::OpenBracket => TokenKind::OpenDelim(DelimToken::Bracket),
+            L::CloseBracket => TokenKind::CloseDelim(DelimToken::Bracket),
+            L::Comma => TokenKind::Comma,
+            L::Dot => TokenKind::Dot,
+            L::DotDot => TokenKind::DotDot,
+            L::DotDotEq => TokenKind::DotDotEq,
+            L::Exclam => TokenKind::Not,
+            L::Plus => TokenKind::BinOp(BinOpToken::Plus),
+            L::Minus => TokenKind::BinOp(BinOpToken::Minus),
+            L::Star => TokenKind::BinOp(BinOpToken::Star),
+            L::Slash => TokenKind::BinOp(BinOpToken::Slash),
+            L::Percent => TokenKind::BinOp(BinOpToken::Percent),
+            L::PlusEq => TokenKind::BinOpEq(BinOpToken::Plus),
+            L::MinusEq => TokenKind::BinOpEq(BinOpToken::Minus),
+            L::StarEq => TokenKind::BinOpEq(BinOpToken::Star),
+            L::SlashEq => TokenKind::BinOpEq(BinOpToken::Slash),
+            L::PercentEq => TokenKind::BinOpEq(BinOpToken::Percent),
+            L::Eq => TokenKind::Eq,
+            L::Colon => TokenKind::Colon,
+            L::PathSep => TokenKind::ModSep,
+            L::Arrow => TokenKind::RArrow,
+            L::FatArrow => TokenKind::FatArrow,
+            L::Lt => TokenKind::Lt,
+            L::Gt => TokenKind::Gt,
+            L::Le => TokenKind::Le,
+            L::Ge => TokenKind::Ge,
+            L::EqEq => TokenKind::EqEq,
+            L::Ne => TokenKind::Ne,
+            L::Shl => TokenKind::BinOp(BinOpToken::Shl),
+            L::Shr => TokenKind::BinOp(BinOpToken::Shr),
+            L::And => TokenKind::BinOp(BinOpToken::And),
+            L::AndAnd => TokenKind::AndAnd,
+            L::Or => TokenKind::BinOp(BinOpToken::Or),
+            L::OrOr => TokenKind::OrOr,
+            L::Caret => TokenKind::BinOp(BinOpToken::Caret),
+            L::Question => TokenKind::Question,
+            L::Pound => TokenKind::Pound,
+            L::At => TokenKind::At,
+            L::Dollar => TokenKind::Dollar,
+            L::Unknown(_) => TokenKind::Unknown,
+            L::Eof => TokenKind::Eof,
+        };
+        Some(Token::new(kind, token.span))
+    }
+}
+
+impl Lit {
+    /// A literal of `kind` written as `text`.
+    pub fn new(kind: LitKind, text: &str) -> Lit {
+        Lit {
+            kind,
+            symbol: Symbol::intern(text),
+        }
+    }
+
+    fn from_lexer(kind: LiteralKind, text: &str) -> Lit {
+        let kind = match kind {
+            LiteralKind::Str {
+                terminated: true, ..
+            }
+            | LiteralKind::RawStr {
+                terminated: true, ..
+            } => LitKind::Str,
+            LiteralKind::Int {
+                empty_digits: false,
+                ..
+            } => LitKind::Int,
+            LiteralKind::Float {
+                empty_exponent: false,
+                ..
+            } => LitKind::Float,
+            LiteralKind::Char { terminated: true } => LitKind::Char,
+            LiteralKind::Byte { terminated: true } => LitKind::Byte,
+            LiteralKind::ByteStr { terminated: true } => LitKind::ByteStr,
+            _ => LitKind::Err,
+        };
+        Lit::new(kind, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(src: &str) -> Vec<TokenKind> {
+        lexer::tokenize(src)
+            .filter_map(|t| Token::from_lexer(&t, src))
+            .map(|t| t.kind)
+            .collect()
+    }
+
+    fn ident(name: &str) -> TokenKind {
+        TokenKind::Ident(Symbol::intern(name))
+    }
+
+    #[test]
+    fn fn_main() {
+        let src = "fn main() {\n    println!(\"hello world\");\n}\n";
+        assert_eq!(
+            convert(src),
+            [
+                TokenKind::Keyword(Keyword::Fn),
+                ident("main"),
+                TokenKind::OpenDelim(DelimToken::Paren),
+                TokenKind::CloseDelim(DelimToken::Paren),
+                TokenKind::OpenDelim(DelimToken::Brace),
+                ident("println"),
+                TokenKind::Not,
+                TokenKind::OpenDelim(DelimToken::Paren),
+                TokenKind::Literal(Lit::new(LitKind::Str, "\"hello world\"")),
+                TokenKind::CloseDelim(DelimToken::Paren),
+                TokenKind::Semi,
+                TokenKind::CloseDelim(DelimToken::Brace),
+            ]
+        );
+    }
+
+    #[test]
+    fn identifiers_are_interned() {
+        let kinds = convert("abc x abc");
+        assert_eq!(kinds[0], kinds[2]);
+        assert_ne!(kinds[0], kinds[1]);
+        let TokenKind::Ident(abc) = kinds[0] else {
+            panic!("expected an identifier");
+        };
+        assert_eq!(abc.to_string(), "abc");
+        assert_eq!(kinds[2].to_string(), "abc");
+    }
+
+    #[test]
+    fn trivia_is_skipped() {
+        assert_eq!(convert(" // a\n /* b */ \t"), []);
+        assert_eq!(convert("a /* b */ c"), [ident("a"), ident("c")]);
+    }
+
+    #[test]
+    fn punctuation() {
+        assert_eq!(
+            convert("= + - * / % : :: -> =>"),
+            [
+                TokenKind::Eq,
+                TokenKind::BinOp(BinOpToken::Plus),
+                TokenKind::BinOp(BinOpToken::Minus),
+                TokenKind::BinOp(BinOpToken::Star),
+                TokenKind::BinOp(BinOpToken::Slash),
+                TokenKind::BinOp(BinOpToken::Percent),
+                TokenKind::Colon,
+                TokenKind::ModSep,
+                TokenKind::RArrow,
+                TokenKind::FatArrow,
+            ]
+        );
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let kinds = convert("+= -= *= /= %=");
+        assert_eq!(
+            kinds,
+            [
+                TokenKind::BinOpEq(BinOpToken::Plus),
+                TokenKind::BinOpEq(BinOpToken::Minus),
+                TokenKind::BinOpEq(BinOpToken::Star),
+                TokenKind::BinOpEq(BinOpToken::Slash),
+                TokenKind::BinOpEq(BinOpToken::Percent),
+            ]
+        );
+        assert_eq!(kinds[3].to_string(), "/=");
+    }
+
+    #[test]
+    fn brackets_comma_dot() {
+        assert_eq!(
+            convert("[a, b.c..d..=]"),
+            [
+                TokenKind::OpenDelim(DelimToken::Bracket),
+                ident("a"),
+                TokenKind::Comma,
+                ident("b"),
+                TokenKind::Dot,
+                ident("c"),
+                TokenKind::DotDot,
+                ident("d"),
+                TokenKind::DotDotEq,
+                TokenKind::CloseDelim(DelimToken::Bracket),
+            ]
+        );
+    }
+
+    #[test]
+    fn single_char_punctuation() {
+        assert_eq!(
+            convert("? # @ $"),
+            [
+                TokenKind::Question,
+                TokenKind::Pound,
+                TokenKind::At,
+                TokenKind::Dollar,
+            ]
+        );
+    }
+
+    #[test]
+    fn comparisons_and_shifts() {
+        assert_eq!(
+            convert("< <= == != >= > << >>"),
+            [
+                TokenKind::Lt,
+                TokenKind::Le,
+                TokenKind::EqEq,
+                TokenKind::Ne,
+                TokenKind::Ge,
+                TokenKind::Gt,
+                TokenKind::BinOp(BinOpToken::Shl),
+                TokenKind::BinOp(BinOpToken::Shr),
+            ]
+        );
+    }
+
+    #[test]
+    fn logical_and_bitwise() {
+        assert_eq!(
+            convert("& && | || ^"),
+            [
+                TokenKind::BinOp(BinOpToken::And),
+                TokenKind::AndAnd,
+                TokenKind::BinOp(BinOpToken::Or),
+                TokenKind::OrOr,
+                TokenKind::BinOp(BinOpToken::Caret),
+            ]
+        );
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(
+            convert(r##"r#"a"# "b"##),
+            [
+                TokenKind::Literal(Lit::new(LitKind::Str, r##"r#"a"#"##)),
+                TokenKind::Literal(Lit::new(LitKind::Err, "\"b")),
+            ]
+        );
+        assert_eq!(
+            convert("0x1F 0x"),
+            [
+                TokenKind::Literal(Lit::new(LitKind::Int, "0x1F")),
+                TokenKind::Literal(Lit::new(LitKind::Err, "0x")),
+            ]
+        );
+        assert_eq!(
+            convert("'a ~"),
+            [TokenKind::Lifetime("'a".to_string()), TokenKind::Unknown]
+        );
+    }
+
+    #[test]
+    fn literals_keep_their_text() {
+        let [TokenKind::Literal(lit)] = &convert("42")[..] else {
+            panic!("expected one literal");
+        };
+        assert_eq!(lit.kind, LitKind::Int);
+        assert_eq!(lit.symbol.to_string(), "42");
+        assert_eq!(lit.to_string(), "42");
+    }
+
+    #[test]
+    fn numeric_literals() {
+        let lit = |kind, text| TokenKind::Literal(Lit::new(kind, text));
+        assert_eq!(
+            convert("1 0x1F 1u8 1.5 1e3 2.0f32 1e"),
+            [
+                lit(LitKind::Int, "1"),
+                lit(LitKind::Int, "0x1F"),
+                lit(LitKind::Int, "1u8"),
+                lit(LitKind::Float, "1.5"),
+                lit(LitKind::Float, "1e3"),
+                lit(LitKind::Float, "2.0f32"),
+                lit(LitKind::Err, "1e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn char_byte_and_bool_literals() {
+        let lit = |kind, text| TokenKind::Literal(Lit::new(kind, text));
+        assert_eq!(
+            convert("'a' b'a' b\"ab\" true false 'a"),
+            [
+                lit(LitKind::Char, "'a'"),
+                lit(LitKind::Byte, "b'a'"),
+                lit(LitKind::ByteStr, "b\"ab\""),
+                lit(LitKind::Bool, "true"),
+                lit(LitKind::Bool, "false"),
+                TokenKind::Lifetime("'a".to_string()),
+            ]
+        );
+        assert_eq!(convert("b'a"), [lit(LitKind::Err, "b'a")]);
+    }
+
+    #[test]
+    fn kinds_compare_and_clone() {
+        let str_lit = TokenKind::Literal(Lit::new(LitKind::Str, "\"1\""));
+        assert_eq!(str_lit, TokenKind::Literal(Lit::new(LitKind::Str, "\"1\"")));
+        assert_ne!(str_lit, TokenKind::Literal(Lit::new(LitKind::Str, "\"2\"")));
+        assert_ne!(str_lit, TokenKind::Literal(Lit::new(LitKind::Int, "\"1\"")));
+        assert_eq!(str_lit.clone(), str_lit);
+        assert_ne!(
+            TokenKind::OpenDelim(DelimToken::Paren),
+            TokenKind::OpenDelim(DelimToken::Brace)
+        );
+    }
+
+    #[test]
+    fn eof() {
+        let src = "x";
+        let tokens: Vec<Token> = lexer::tokenize_with_eof(src)
+            .filter_map(|t| Token::from_lexer(&t, src))
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::new(ident("x"), Span { lo: 0, hi: 1 }),
+                Token::new(TokenKind::Eof, Span { lo: 1, hi: 1 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn span_is_preserved() {
+        let src = "fn  main";
+        let lexed = lexer::tokenize_to_vec(src);
+        let main = Token::from_lexer(&lexed[2], src).unwrap();
+        assert_eq!(main.span, lexed[2].span);
+        assert_eq!(main.span, Span { lo: 4, hi: 8 });
+    }
+}
@@ -0,0 +1,675 @@
+//! Token trees: tokens grouped by their delimiters.
+
+use std::fmt;
+use std::ops::Index;
+
+use super::token::{DelimToken, Token, TokenKind};
+use crate::lexer::Span;
+
+/// A sequence of token trees.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TokenStream(Vec<TreeAndSpacing>);
+
+pub type TreeAndSpacing = (TokenTree, Spacing);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenTree {
+    Token(Token),
+    /// A delimited group, not including the delimiters themselves.
+    Delimited(DelimSpan, DelimToken, TokenStream),
+}
+
+/// The spans of the opening and closing delimiters of a group.
+///
+/// Groups built without source positions have default spans.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DelimSpan {
+    pub open: Span,
+    pub close: Span,
+}
+
+impl DelimSpan {
+    /// The span of the whole group, from its opening delimiter to its
+    /// closing one.
+    pub fn entire(&self) -> Span {
+        self.open.to(self.close)
+    }
+}
+
+/// Whether a tree is immediately followed by the next one, as the two `:`
+/// in `::` would be.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Spacing {
+    Alone,
+    Joint,
+}
+
+impl fmt::Display for TokenStream {
+    /// Renders the stream as source text, separating trees with a space
+    /// unless they are [`Spacing::Joint`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (tree, spacing)) in self.0.iter().enumerate() {
+            write!(f, "{tree}")?;
+            if *spacing == Spacing::Alone && i + 1 < self.0.len() {
+                f.write_str(" ")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TokenTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenTree::Token(token) => write!(f, "{token}"),
+            TokenTree::Delimited(_, delim, stream) => {
+                write!(f, "{}{stream}{}", delim.open_str(), delim.close_str())
+            }
+        }
+    }
+}
+
+/// Why a token sequence could not be grouped into a [`TokenStream`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenStreamError {
+    /// A closing delimiter with no opening one, as in `)`.
+    UnmatchedClose(DelimToken),
+    /// An opening delimiter that is never closed, as in `(`.
+    Unclosed(DelimToken),
+    /// A group closed by the wrong delimiter, as in `(]`.
+    MismatchedClose { open: DelimToken, close: DelimToken },
+    /// Groups nested deeper than the limit, see
+    /// [`TokenStream::from_tokens_with_max_depth`].
+    TooDeep,
+}
+
+impl fmt::Display for TokenStreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenStreamError::UnmatchedClose(delim) => {
+                write!(f, "unexpected closing delimiter `{}`", delim.close_str())
+            }
+            TokenStreamError::Unclosed(delim) => {
+                write!(f, "unclosed delimiter `{}`", delim.open_str())
+            }
+            TokenStreamError::MismatchedClose { open, close } => write!(
+                f,
+                "delimiter `{}` closed by `{}`",
+                open.open_str(),
+                close.close_str()
+            ),
+            TokenStreamError::TooDeep => f.write_str("delimiters nested too deeply"),
+        }
+    }
+}
+
+impl std::error::Error for TokenStreamError {}
+
+/// How deeply [`TokenStream::from_tokens`] lets groups nest.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+impl TokenStream {
+    /// An empty stream, to be filled in with [`TokenStream::push`].
+    pub fn new() -> TokenStream {
+        TokenStream::default()
+    }
+
+    /// Appends a tree to the end of the stream.
+    pub fn push(&mut self, tree: TokenTree, spacing: Spacing) {
+        self.0.push((tree, spacing));
+    }
+
+    /// Groups `tokens` into trees by matching up their delimiters. Input
+    /// ends at the first [`TokenKind::Eof`], if any.
+    ///
+    /// Token spans fill in each group's [`DelimSpan`], and mark trees that
+    /// are immediately followed by the next token as [`Spacing::Joint`].
+    /// Tokens with [`Span::dummy`] spans count as having no position, so
+    /// their groups get default spans and their trees are all
+    /// [`Spacing::Alone`].
+    ///
+    /// Fails with [`TokenStreamError::TooDeep`] if groups nest more than
+    /// [`DEFAULT_MAX_DEPTH`] deep.
+    pub fn from_tokens(
+        tokens: impl Iterator<Item = Token>,
+    ) -> Result<TokenStream, TokenStreamError> {
+        TokenStream::from_tokens_with_max_depth(tokens, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Like [`TokenStream::from_tokens`], but allows groups to nest
+    /// `max_depth` deep.
+    pub fn from_tokens_with_max_depth(
+        mut tokens: impl Iterator<Item = Token>,
+        max_depth: usize,
+    ) -> Result<TokenStream, TokenStreamError> {
+        let (stream, _) = parse_trees(&mut tokens, None, max_depth)?;
+        Ok(stream)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TreeAndSpacing> {
+        self.0.iter()
+    }
+
+    /// Number of trees at the top level of the stream.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&TreeAndSpacing> {
+        self.0.get(index)
+    }
+
+    /// A cursor positioned at the first tree of the stream.
+    pub fn cursor(&self) -> TokenStreamCursor<'_> {
+        TokenStreamCursor::new(self)
+    }
+}
+
+impl FromIterator<TreeAndSpacing> for TokenStream {
+    fn from_iter<I: IntoIterator<Item = TreeAndSpacing>>(trees: I) -> TokenStream {
+        TokenStream(trees.into_iter().collect())
+    }
+}
+
+/// Appends trees to the end of the stream. Since a [`TokenStream`] iterates
+/// over its trees, `a.extend(b)` appends all of `b` to `a`.
+impl Extend<TreeAndSpacing> for TokenStream {
+    fn extend<I: IntoIterator<Item = TreeAndSpacing>>(&mut self, trees: I) {
+        self.0.extend(trees);
+    }
+}
+
+impl Index<usize> for TokenStream {
+    type Output = TreeAndSpacing;
+
+    fn index(&self, index: usize) -> &TreeAndSpacing {
+        &self.0[index]
+    }
+}
+
+impl IntoIterator for TokenStream {
+    type Item = TreeAndSpacing;
+    type IntoIter = std::vec::IntoIter<TreeAndSpacing>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a TokenStream {
+    type Item = &'a TreeAndSpacing;
+    type IntoIter = std::slice::Iter<'a, TreeAndSpacing>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// Walks a [`TokenStream`], either a tree at a time with
+/// [`TokenStreamCursor::next_tree`] or, as an iterator, a token at a time.
+///
+/// Iterating descends into delimited groups, yielding their opening and
+/// closing delimiters as tokens around their contents, so the stream comes
+/// back out as the flat sequence it was built from.
+#[derive(Debug, Clone)]
+pub struct TokenStreamCursor<'a> {
+    /// The groups being walked, innermost last.
+    frames: Vec<Frame<'a>>,
+}
+
+#[derive(Debug, Clone)]
+struct Frame<'a> {
+    stream: &'a TokenStream,
+    /// Index of the next tree in `stream`.
+    index: usize,
+    /// The token to yield once `stream` is exhausted, `None` at the top
+    /// level.
+    close: Option<Token>,
+}
+
+impl<'a> TokenStreamCursor<'a> {
+    pub fn new(stream: &'a TokenStream) -> TokenStreamCursor<'a> {
+        TokenStreamCursor {
+            frames: vec![Frame {
+                stream,
+                index: 0,
+                close: None,
+            }],
+        }
+    }
+
+    /// The next tree of the innermost group being walked, without consuming
+    /// it.
+    pub fn peek_tree(&self) -> Option<&'a TreeAndSpacing> {
+        let frame = self.frames.last()?;
+        frame.stream.get(frame.index)
+    }
+
+    /// Consumes the next tree of the innermost group being walked, without
+    /// descending into it. Returns `None` at the end of the group, whose
+    /// closing delimiter the iterator still yields.
+    pub fn next_tree(&mut self) -> Option<&'a TreeAndSpacing> {
+        let tree = self.peek_tree()?;
+        self.frames.last_mut()?.index += 1;
+        Some(tree)
+    }
+}
+
+impl Iterator for TokenStreamCursor<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let Some((tree, _)) = self.next_tree() else {
+            return self.frames.pop()?.close;
+        };
+        match tree {
+            TokenTree::Token(token) => Some(token.clone()),
+            TokenTree::Delimited(span, delim, stream) => {
+                self.frames.push(Frame {
+                    stream,
+                    index: 0,
+                    close: Some(Token::new(TokenKind::CloseDelim(delim.clone()), span.close)),
+                });
+                Some(Token::new(TokenKind::OpenDelim(delim.clone()), span.open))
+            }
+        }
+    }
+}
+
+/// Collects trees until the delimiter closing `open`, or until the end of
+/// input if `open` is `None`. Also returns the span of the closing delimiter.
+///
+/// Groups may nest `depth_left` more levels inside this one.
+fn parse_trees(
+    tokens: &mut impl Iterator<Item = Token>,
+    open: Option<&DelimToken>,
+    depth_left: usize,
+) -> Result<(TokenStream, Option<Span>), TokenStreamError> {
+    let mut trees: Vec<TreeAndSpacing> = Vec::new();
+    // Where the last tree in `trees` ends, if known.
+    let mut last_hi = None;
+    loop {
+        let token = match tokens.next() {
+            Some(token) if token.kind != TokenKind::Eof => token,
+            _ => {
+                return match open {
+                    Some(open) => Err(TokenStreamError::Unclosed(open.clone())),
+                    None => Ok((TokenStream(trees), None)),
+                };
+            }
+        };
+        let span = (token.span != Span::dummy()).then_some(token.span);
+        if let (Some(last_hi), Some(span), Some(last)) = (last_hi, span, trees.last_mut()) {
+            if last_hi == span.lo {
+                last.1 = Spacing::Joint;
+            }
+        }
+        match token.kind {
+            TokenKind::OpenDelim(delim) => {
+                if depth_left == 0 {
+                    return Err(TokenStreamError::TooDeep);
+                }
+                let (inner, close_span) = parse_trees(tokens, Some(&delim), depth_left - 1)?;
+                let delim_span = DelimSpan {
+                    open: span.unwrap_or_default(),
+                    close: close_span.unwrap_or_default(),
+                };
+                trees.push((
+                    TokenTree::Delimited(delim_span, delim, inner),
+                    Spacing::Alone,
+                ));
+                last_hi = close_span.map(|span| span.hi);
+            }
+            TokenKind::CloseDelim(close) => {
+                return match open {
+                    Some(open) if *open == close => Ok((TokenStream(trees), span)),
+                    Some(open) => Err(TokenStreamError::MismatchedClose {
+                        open: open.clone(),
+                        close,
+                    }),
+                    None => Err(TokenStreamError::UnmatchedClose(close)),
+                };
+            }
+            _ => {
+                trees.push((TokenTree::Token(token), Spacing::Alone));
+                last_hi = span.map(|span| span.hi);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::symbol::Symbol;
+    use crate::parser::token::{Lit, LitKind};
+
+    fn tok(kind: TokenKind) -> Token {
+        Token::new(kind, Span::default())
+    }
+
+    fn ident() -> TokenKind {
+        TokenKind::Ident(Symbol::intern("a"))
+    }
+
+    fn open(delim: DelimToken) -> Token {
+        tok(TokenKind::OpenDelim(delim))
+    }
+
+    fn close(delim: DelimToken) -> Token {
+        tok(TokenKind::CloseDelim(delim))
+    }
+
+    fn alone(tree: TokenTree) -> TreeAndSpacing {
+        (tree, Spacing::Alone)
+    }
+
+    #[test]
+    fn single_group() {
+        let tokens = vec![
+            open(DelimToken::Paren),
+            tok(ident()),
+            close(DelimToken::Paren),
+        ];
+        let stream = TokenStream::from_tokens(tokens.into_iter()).unwrap();
+        let inner = TokenStream(vec![alone(TokenTree::Token(tok(ident())))]);
+        assert_eq!(
+            stream,
+            TokenStream(vec![alone(TokenTree::Delimited(
+                DelimSpan::default(),
+                DelimToken::Paren,
+                inner
+            ))])
+        );
+    }
+
+    #[test]
+    fn nested_groups() {
+        let tokens = vec![
+            open(DelimToken::Brace),
+            open(DelimToken::Paren),
+            close(DelimToken::Paren),
+            close(DelimToken::Brace),
+            tok(TokenKind::Semi),
+        ];
+        let stream = TokenStream::from_tokens(tokens.into_iter()).unwrap();
+        let parens = TokenTree::Delimited(
+            DelimSpan::default(),
+            DelimToken::Paren,
+            TokenStream(Vec::new()),
+        );
+        let braces = TokenTree::Delimited(
+            DelimSpan::default(),
+            DelimToken::Brace,
+            TokenStream(vec![alone(parens)]),
+        );
+        assert_eq!(
+            stream,
+            TokenStream(vec![
+                alone(braces),
+                alone(TokenTree::Token(tok(TokenKind::Semi)))
+            ])
+        );
+    }
+
+    #[test]
+    fn unclosed_delimiter() {
+        let tokens = vec![open(DelimToken::Paren), tok(ident())];
+        assert_eq!(
+            TokenStream::from_tokens(tokens.into_iter()),
+            Err(TokenStreamError::Unclosed(DelimToken::Paren))
+        );
+    }
+
+    #[test]
+    fn unmatched_and_mismatched_close() {
+        let tokens = vec![close(DelimToken::Brace)];
+        assert_eq!(
+            TokenStream::from_tokens(tokens.into_iter()),
+            Err(TokenStreamError::UnmatchedClose(DelimToken::Brace))
+        );
+        let tokens = vec![open(DelimToken::Paren), close(DelimToken::Brace)];
+        assert_eq!(
+            TokenStream::from_tokens(tokens.into_iter()),
+            Err(TokenStreamError::MismatchedClose {
+                open: DelimToken::Paren,
+                close: DelimToken::Brace,
+            })
+        );
+    }
+
+    fn lexed(input: &str) -> Vec<Token> {
+        crate::lexer::tokenize(input)
+            .filter_map(|t| Token::from_lexer(&t, input))
+            .collect()
+    }
+
+    fn unspanned(input: &str) -> impl Iterator<Item = Token> {
+        lexed(input)
+            .into_iter()
+            .map(|t| Token::new(t.kind, Span::dummy()))
+    }
+
+    fn spacings(stream: &TokenStream) -> Vec<Spacing> {
+        stream.0.iter().map(|(_, spacing)| *spacing).collect()
+    }
+
+    #[test]
+    fn joint_spacing() {
+        let stream = TokenStream::from_tokens(lexed(";;").into_iter()).unwrap();
+        assert_eq!(spacings(&stream), [Spacing::Joint, Spacing::Alone]);
+    }
+
+    #[test]
+    fn alone_spacing() {
+        let stream = TokenStream::from_tokens(lexed("; ;").into_iter()).unwrap();
+        assert_eq!(spacings(&stream), [Spacing::Alone, Spacing::Alone]);
+    }
+
+    #[test]
+    fn spacing_around_groups() {
+        let stream = TokenStream::from_tokens(lexed("(a )b").into_iter()).unwrap();
+        assert_eq!(spacings(&stream), [Spacing::Joint, Spacing::Alone]);
+        let TokenTree::Delimited(_, _, inner) = &stream.0[0].0 else {
+            panic!("expected a group");
+        };
+        assert_eq!(spacings(inner), [Spacing::Alone]);
+        let stream = TokenStream::from_tokens(unspanned("(a)b")).unwrap();
+        assert_eq!(spacings(&stream), [Spacing::Alone, Spacing::Alone]);
+    }
+
+    #[test]
+    fn delim_spans() {
+        let stream = TokenStream::from_tokens(lexed("{ (a) }").into_iter()).unwrap();
+        let TokenTree::Delimited(outer_span, _, outer) = &stream[0].0 else {
+            panic!("expected a group");
+        };
+        assert_eq!(outer_span.open, Span { lo: 0, hi: 1 });
+        assert_eq!(outer_span.close, Span { lo: 6, hi: 7 });
+        assert_eq!(outer_span.entire(), Span { lo: 0, hi: 7 });
+        let TokenTree::Delimited(inner_span, _, _) = &outer[0].0 else {
+            panic!("expected a group");
+        };
+        assert_eq!(inner_span.open, Span { lo: 2, hi: 3 });
+        assert_eq!(inner_span.close, Span { lo: 4, hi: 5 });
+
+        let stream = TokenStream::from_tokens(unspanned("()")).unwrap();
+        let TokenTree::Delimited(span, _, _) = &stream[0].0 else {
+            panic!("expected a group");
+        };
+        assert_eq!(*span, DelimSpan::default());
+    }
+
+    #[test]
+    fn build_stream() {
+        let mut stream = TokenStream::new();
+        assert!(stream.is_empty());
+        stream.push(TokenTree::Token(tok(ident())), Spacing::Joint);
+        let mut rest = TokenStream::new();
+        rest.push(TokenTree::Token(tok(TokenKind::Semi)), Spacing::Alone);
+        stream.extend(rest);
+        assert_eq!(
+            stream,
+            TokenStream(vec![
+                (TokenTree::Token(tok(ident())), Spacing::Joint),
+                alone(TokenTree::Token(tok(TokenKind::Semi))),
+            ])
+        );
+        assert_eq!(stream.to_string(), "a;");
+    }
+
+    #[test]
+    fn collect_and_extend() {
+        let semi = || TokenTree::Token(tok(TokenKind::Semi));
+        let trees = vec![
+            alone(TokenTree::Token(tok(ident()))),
+            (semi(), Spacing::Joint),
+        ];
+        let mut stream: TokenStream = trees.clone().into_iter().collect();
+        assert_eq!(stream.len(), 2);
+        assert_eq!(stream, TokenStream(trees));
+
+        stream.extend(vec![alone(semi())]);
+        let copy = stream.clone();
+        stream.extend(copy);
+        assert_eq!(stream.len(), 6);
+        assert_eq!(stream.to_string(), "a ;; a ;;");
+    }
+
+    #[test]
+    fn iterate_stream() {
+        let stream = TokenStream(vec![
+            alone(TokenTree::Token(tok(ident()))),
+            (TokenTree::Token(tok(TokenKind::Semi)), Spacing::Joint),
+        ]);
+        assert_eq!(stream.len(), 2);
+        assert!(!stream.is_empty());
+        assert_eq!(stream[1].1, Spacing::Joint);
+        assert_eq!(stream.get(2), None);
+
+        let kinds: Vec<&TokenKind> = stream
+            .iter()
+            .map(|(tree, _)| match tree {
+                TokenTree::Token(token) => &token.kind,
+                TokenTree::Delimited(..) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(kinds, [&ident(), &TokenKind::Semi]);
+        assert_eq!((&stream).into_iter().count(), 2);
+
+        let owned: Vec<TreeAndSpacing> = stream.into_iter().collect();
+        assert_eq!(owned[0], alone(TokenTree::Token(tok(ident()))));
+        assert!(TokenStream(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn display() {
+        let call = TokenStream(vec![
+            (TokenTree::Token(tok(ident())), Spacing::Joint),
+            (
+                TokenTree::Delimited(
+                    DelimSpan::default(),
+                    DelimToken::Paren,
+                    TokenStream(vec![alone(TokenTree::Token(tok(TokenKind::Literal(
+                        Lit::new(LitKind::Str, "\"\""),
+                    ))))]),
+                ),
+                Spacing::Joint,
+            ),
+            alone(TokenTree::Token(tok(TokenKind::Semi))),
+        ]);
+        let stream = TokenStream(vec![
+            alone(TokenTree::Token(tok(ident()))),
+            alone(TokenTree::Delimited(
+                DelimSpan::default(),
+                DelimToken::Brace,
+                call,
+            )),
+        ]);
+        assert_eq!(stream.to_string(), "a {a(\"\");}");
+    }
+
+    #[test]
+    fn stops_at_eof() {
+        let tokens = vec![tok(TokenKind::Semi), Token::eof(), tok(ident())];
+        let stream = TokenStream::from_tokens(tokens.into_iter()).unwrap();
+        assert_eq!(
+            stream,
+            TokenStream(vec![alone(TokenTree::Token(tok(TokenKind::Semi)))])
+        );
+    }
+
+    #[test]
+    fn cursor_flattens_groups() {
+        let tokens = lexed("a (b [c] {}) d");
+        let stream = TokenStream::from_tokens(tokens.clone().into_iter()).unwrap();
+        let flat: Vec<Token> = stream.cursor().collect();
+        assert_eq!(flat, tokens);
+        assert_eq!(TokenStream::new().cursor().next(), None);
+    }
+
+    #[test]
+    fn cursor_next_tree() {
+        let stream = TokenStream::from_tokens(lexed("a (b c) d").into_iter()).unwrap();
+        let mut cursor = stream.cursor();
+        assert_eq!(cursor.peek_tree(), Some(&stream[0]));
+        assert_eq!(cursor.next_tree(), Some(&stream[0]));
+        assert_eq!(
+            cursor.next().map(|t| t.kind),
+            Some(TokenKind::OpenDelim(DelimToken::Paren))
+        );
+        let TokenTree::Delimited(_, _, inner) = &stream[1].0 else {
+            panic!("expected a group");
+        };
+        assert_eq!(cursor.next_tree(), Some(&inner[0]));
+        assert_eq!(cursor.next_tree(), Some(&inner[1]));
+        assert_eq!(cursor.next_tree(), None);
+        assert_eq!(cursor.peek_tree(), None);
+        assert_eq!(
+            cursor.next().map(|t| t.kind),
+            Some(TokenKind::CloseDelim(DelimToken::Paren))
+        );
+        assert_eq!(cursor.next_tree(), Some(&stream[2]));
+        assert_eq!(cursor.next(), None);
+        assert_eq!(cursor.next(), None);
+    }
+
+    fn nested_parens(depth: usize) -> impl Iterator<Item = Token> {
+        let opens = std::iter::repeat_with(|| open(DelimToken::Paren)).take(depth);
+        let closes = std::iter::repeat_with(|| close(DelimToken::Paren)).take(depth);
+        opens.chain(closes)
+    }
+
+    #[test]
+    fn depth_limit() {
+        assert!(TokenStream::from_tokens(nested_parens(DEFAULT_MAX_DEPTH)).is_ok());
+        assert_eq!(
+            TokenStream::from_tokens(nested_parens(DEFAULT_MAX_DEPTH + 1)),
+            Err(TokenStreamError::TooDeep)
+        );
+        assert_eq!(
+            TokenStream::from_tokens(nested_parens(1_000_000)),
+            Err(TokenStreamError::TooDeep)
+        );
+        assert!(TokenStream::from_tokens_with_max_depth(nested_parens(2), 2).is_ok());
+        assert_eq!(
+            TokenStream::from_tokens_with_max_depth(nested_parens(3), 2),
+            Err(TokenStreamError::TooDeep)
+        );
+        assert_eq!(
+            TokenStream::from_tokens_with_max_depth(nested_parens(0), 0),
+            Ok(TokenStream::new())
+        );
+
+        let stream = TokenStream::from_tokens_with_max_depth(lexed("(a);").into_iter(), 1).unwrap();
+        assert_eq!(spacings(&stream), [Spacing::Joint, Spacing::Alone]);
+        assert_eq!(
+            TokenStream::from_tokens_with_max_depth(lexed("((a))").into_iter(), 1),
+            Err(TokenStreamError::TooDeep)
+        );
+    }
+}